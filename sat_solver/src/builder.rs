@@ -0,0 +1,89 @@
+use crate::{Clause, Lit, Solver, SolverConfig, Var};
+
+/// Collects a formula clause by clause while handing out variables on the
+/// fly, so encoding code needn't count them up front. The variable count is
+/// the highest variable used, whether it came from `fresh_var()` or appeared
+/// in a clause directly.
+#[derive(Debug, Clone, Default)]
+pub struct CnfBuilder {
+    clauses: Vec<Clause>,
+    num_vars: usize,
+}
+
+impl CnfBuilder {
+    pub fn new() -> Self {
+        CnfBuilder::default()
+    }
+
+    /// Allocates a variable above every one used so far and returns its
+    /// positive literal.
+    pub fn fresh_var(&mut self) -> Lit {
+        self.num_vars += 1;
+        Lit::new(self.num_vars as Var, true)
+    }
+
+    pub fn add_clause(&mut self, lits: impl IntoIterator<Item = Lit>) {
+        let clause: Clause = lits.into_iter().collect();
+        for lit in &clause {
+            self.num_vars = self.num_vars.max(lit.var() as usize);
+        }
+        self.clauses.push(clause);
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.num_vars
+    }
+
+    pub fn clauses(&self) -> &[Clause] {
+        &self.clauses
+    }
+
+    pub fn build(self) -> Solver {
+        Solver::new(self.clauses, self.num_vars)
+    }
+
+    /// Like `build()`, with non-default solver options.
+    pub fn build_with_config(self, config: SolverConfig) -> Solver {
+        Solver::with_config(self.clauses, self.num_vars, config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::lits;
+    use crate::SolveResult;
+
+    #[test]
+    fn counts_fresh_and_direct_variables() {
+        let mut builder = CnfBuilder::new();
+        assert_eq!(builder.fresh_var(), Lit::from(1));
+        builder.add_clause(lits(&[1, -5]));
+        assert_eq!(builder.num_vars(), 5);
+        // fresh variables come after every variable used so far
+        assert_eq!(builder.fresh_var(), Lit::from(6));
+        builder.add_clause(lits(&[-2]));
+        assert_eq!(builder.num_vars(), 6);
+        assert_eq!(builder.clauses().len(), 2);
+    }
+
+    #[test]
+    fn tseitin_xor_solves() {
+        // x <-> a ^ b
+        let mut builder = CnfBuilder::new();
+        let (a, b, x) = (builder.fresh_var(), builder.fresh_var(), builder.fresh_var());
+        builder.add_clause([-x, a, b]);
+        builder.add_clause([-x, -a, -b]);
+        builder.add_clause([x, -a, b]);
+        builder.add_clause([x, a, -b]);
+        let mut solver = builder.build();
+        for (va, vb) in [(false, false), (false, true), (true, false), (true, true)] {
+            let assumed = [if va { a } else { -a }, if vb { b } else { -b }];
+            assert_eq!(solver.solve_under_assumptions(&assumed), SolveResult::Sat);
+            assert_eq!(solver.model().unwrap()[3], va != vb);
+            let wrong = if va != vb { -x } else { x };
+            let clash = [assumed[0], assumed[1], wrong];
+            assert_eq!(solver.solve_under_assumptions(&clash), SolveResult::Unsat);
+        }
+    }
+}
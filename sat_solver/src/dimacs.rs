@@ -0,0 +1,534 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::{Clause, Lit, LitInt, Var};
+
+/// A malformed DIMACS input, with the 1-based line where it was detected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        ParseError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+fn parse_int<T: std::str::FromStr>(token: &str, line: usize) -> Result<T, ParseError> {
+    token
+        .parse()
+        .map_err(|_| ParseError::new(line, format!("expected integer, found '{}'", token)))
+}
+
+// Largest variable a formula may use: every literal has to keep its negation
+// representable, which rules out LitInt::MIN.
+const MAX_VAR: usize = LitInt::MAX as usize;
+
+fn check_var(var: usize, token: &str, line: usize) -> Result<(), ParseError> {
+    if var > MAX_VAR {
+        return Err(ParseError::new(
+            line,
+            format!("variable out of range in '{}', the largest allowed is {}", token, MAX_VAR),
+        ));
+    }
+    Ok(())
+}
+
+// Parses a literal, or the 0 ending a clause.
+fn parse_lit(token: &str, line: usize) -> Result<LitInt, ParseError> {
+    let n: LitInt = parse_int(token, line)?;
+    check_var(n.unsigned_abs() as usize, token, line)?;
+    Ok(n)
+}
+
+// A comment line is a "c" on its own or followed by whitespace, so a line
+// that merely starts with the letter, like a mistyped "cnf 3 2", is reported
+// as malformed instead of skipped.
+fn is_comment(line: &str) -> bool {
+    line.strip_prefix('c')
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+}
+
+// Splits `input` into whitespace-separated tokens, each with its 1-based line
+// number. Comment lines are dropped wherever they occur, including between
+// the literals of a clause. SATLIB instances end with a "%" line followed by
+// a stray "0", so the input stops at such a line.
+fn tokens(input: &str) -> impl Iterator<Item = (usize, &str)> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, l)| !is_comment(l))
+        .take_while(|(_, l)| !l.starts_with("%"))
+        .flat_map(|(line_no, l)| l.split_whitespace().map(move |token| (line_no, token)))
+}
+
+// Variables listed on "c ind ... 0" comment lines, the independent support
+// annotation of projected model counters such as ApproxMC. A line may be cut
+// short of its 0 and lists can span several lines.
+fn independent_support(input: &str) -> Result<Vec<Var>, ParseError> {
+    let mut vars = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('%') {
+            break;
+        }
+        let Some(rest) = line.strip_prefix("c ind") else {
+            continue;
+        };
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            continue;
+        }
+        for token in rest.split_whitespace() {
+            match parse_int::<Var>(token, i + 1)? {
+                0 => break,
+                var => {
+                    check_var(var as usize, token, i + 1)?;
+                    vars.push(var);
+                }
+            }
+        }
+    }
+    Ok(vars)
+}
+
+/// A parsed DIMACS CNF formula.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dimacs {
+    pub clauses: Vec<Clause>,
+    /// Number of variables: the header's count, or the largest variable
+    /// used if the header understates it. Variables the header declares but
+    /// no clause uses are only counted up to the input's length in bytes, so
+    /// a header claiming billions of them can't make the solver allocate for
+    /// all of them.
+    pub num_vars: usize,
+    /// Number of variables the `p cnf` header declared.
+    pub declared_vars: usize,
+    /// Number of clauses the header declared, if it gave one. It counts
+    /// soft clauses and XOR constraints too, so a formula whose
+    /// `clause_count()` differs is truncated or has extra clauses.
+    pub declared_clauses: Option<usize>,
+    /// Soft clauses of a `p wcnf` formula with their weights; `clauses` then
+    /// holds its hard clauses.
+    pub soft: Vec<(u64, Clause)>,
+    /// XOR constraints given on `x` lines: the XOR of each one's literals must
+    /// be true.
+    pub xors: Vec<Clause>,
+    /// Weight marking hard clauses in a `p wcnf` header, `u64::MAX` if the
+    /// header gives none; None for a plain CNF formula.
+    pub top: Option<u64>,
+    /// Variables listed on `c ind 1 2 3 0` comment lines, the independent
+    /// support of projected model counters; empty if there are none. Solving
+    /// ignores them.
+    pub independent_support: Vec<Var>,
+}
+
+/// Parses a DIMACS CNF formula, returning its clauses and number of
+/// variables, as `Dimacs::parse` does.
+pub fn parse_dimacs(input: &str) -> Result<(Vec<Clause>, usize), ParseError> {
+    Dimacs::parse(input).map(|dimacs| (dimacs.clauses, dimacs.num_vars))
+}
+
+impl Dimacs {
+    /// Parses a DIMACS CNF formula. A lone `0` is kept as an empty clause,
+    /// which makes the formula unsatisfiable. Literals over variables beyond
+    /// the declared count are accepted and grow `num_vars`, since real-world
+    /// headers are often understated; compare it against `declared_vars` to
+    /// detect that.
+    ///
+    /// A `p wcnf <vars> <clauses> <top>` header is accepted too: every clause
+    /// then starts with its weight, and those weighing at least `top` are
+    /// hard while the rest go to `soft`.
+    ///
+    /// A clause written with an `x` in front, as in `x 1 -2 3 0` or
+    /// `x1 -2 3 0`, is an XOR constraint and goes to `xors` instead.
+    pub fn parse(input: &str) -> Result<Dimacs, ParseError> {
+        let mut clauses: Vec<Clause> = Vec::new();
+        let mut soft: Vec<(u64, Clause)> = Vec::new();
+        let mut num_vars: Option<usize> = None;
+        let mut declared_clauses: Option<usize> = None;
+        let mut top: Option<u64> = None;
+        let mut max_var = 0;
+
+        let mut current_clause: Clause = Vec::new();
+        let mut xors: Vec<Clause> = Vec::new();
+        let mut is_xor = false;
+        // weight of the current wcnf clause, once read
+        let mut weight: Option<u64> = None;
+        let mut last_line = 0;
+
+        let mut tokens = tokens(input).peekable();
+        while let Some((line_no, token)) = tokens.next() {
+            last_line = line_no;
+
+            if token == "p" {
+                // the header's fields are the rest of its line
+                let mut parts = vec![token];
+                while let Some(&(_, field)) = tokens.peek().filter(|&&(l, _)| l == line_no) {
+                    parts.push(field);
+                    tokens.next();
+                }
+                if parts.len() < 3 {
+                    return Err(ParseError::new(
+                        line_no,
+                        "expected problem line of the form 'p cnf <vars> <clauses>'",
+                    ));
+                }
+                match parts[1] {
+                    "cnf" => {}
+                    "wcnf" => {
+                        top = Some(match parts.get(4) {
+                            Some(field) => parse_int(field, line_no)?,
+                            None => u64::MAX,
+                        })
+                    }
+                    format => {
+                        return Err(ParseError::new(
+                            line_no,
+                            format!("unsupported format '{}', expected 'cnf' or 'wcnf'", format),
+                        ));
+                    }
+                }
+                let declared_vars = parse_int(parts[2], line_no)?;
+                check_var(declared_vars, parts[2], line_no)?;
+                num_vars = Some(declared_vars);
+                declared_clauses = parts.get(3).map(|field| parse_int(field, line_no)).transpose()?;
+                continue;
+            }
+
+            if num_vars.is_none() {
+                return Err(ParseError::new(line_no, "clause before 'p cnf' header"));
+            }
+
+            let token = match token.strip_prefix('x') {
+                Some(rest) if current_clause.is_empty() && !is_xor => {
+                    is_xor = true;
+                    if rest.is_empty() {
+                        continue;
+                    }
+                    rest
+                }
+                _ => token,
+            };
+
+            if let (Some(_), None, false) = (top, weight, is_xor) {
+                weight = Some(parse_int(token, line_no)?);
+                continue;
+            }
+
+            // only 0 ends a clause, wherever the line breaks fall
+            let n = parse_lit(token, line_no)?;
+            if n == 0 && is_xor {
+                xors.push(current_clause.clone());
+                current_clause.clear();
+                is_xor = false;
+            } else if n == 0 {
+                match (weight.take(), top) {
+                    (Some(w), Some(top)) if w < top => soft.push((w, current_clause.clone())),
+                    _ => clauses.push(current_clause.clone()),
+                }
+                current_clause.clear();
+            } else {
+                let lit = Lit::from(n);
+                max_var = max_var.max(lit.var() as usize);
+                current_clause.push(lit);
+            }
+        }
+
+        if !current_clause.is_empty() || weight.is_some() || is_xor {
+            return Err(ParseError::new(
+                last_line,
+                "last clause is not terminated by 0",
+            ));
+        }
+
+        match num_vars {
+            Some(declared_vars) => Ok(Dimacs {
+                clauses,
+                num_vars: declared_vars.min(input.len()).max(max_var),
+                declared_vars,
+                declared_clauses,
+                soft,
+                xors,
+                top,
+                independent_support: independent_support(input)?,
+            }),
+            None => Err(ParseError::new(
+                input.lines().count().max(1),
+                "missing 'p cnf' header",
+            )),
+        }
+    }
+
+    /// Like `parse`, but also rejects a formula whose clause count differs
+    /// from the one its header declares, reporting it at the last line.
+    pub fn parse_strict(input: &str) -> Result<Dimacs, ParseError> {
+        let dimacs = Dimacs::parse(input)?;
+        match dimacs.declared_clauses {
+            Some(declared) if declared != dimacs.clause_count() => Err(ParseError::new(
+                input.lines().count().max(1),
+                format!(
+                    "header declares {} clauses but {} were found",
+                    declared,
+                    dimacs.clause_count()
+                ),
+            )),
+            _ => Ok(dimacs),
+        }
+    }
+
+    /// Number of clauses read, counting soft clauses and XOR constraints.
+    pub fn clause_count(&self) -> usize {
+        self.clauses.len() + self.soft.len() + self.xors.len()
+    }
+}
+
+/// Parses a list of assumption literals, or any other list of DIMACS
+/// literals ended by a `0`, such as an assignment: on one line or spread
+/// over several, with comment lines skipped.
+pub fn parse_assumptions(input: &str) -> Result<Vec<Lit>, ParseError> {
+    let mut lits = Vec::new();
+    let mut last_line = 0;
+    for (line_no, token) in tokens(input) {
+        last_line = line_no;
+        match parse_lit(token, line_no)? {
+            0 => return Ok(lits),
+            n => lits.push(Lit::from(n)),
+        }
+    }
+    Err(ParseError::new(last_line.max(1), "literals are not terminated by 0"))
+}
+
+/// Writes `clauses` as a DIMACS CNF formula over `num_vars` variables, in the
+/// form `parse_dimacs` reads back: a `p cnf` header, then one clause per line
+/// terminated by `0`.
+pub fn write_dimacs<W: Write>(clauses: &[Clause], num_vars: usize, out: &mut W) -> io::Result<()> {
+    writeln!(out, "p cnf {} {}", num_vars, clauses.len())?;
+    for clause in clauses {
+        for lit in clause {
+            write!(out, "{} ", lit)?;
+        }
+        writeln!(out, "0")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::clauses;
+
+    #[test]
+    fn parses_clauses_across_lines_and_comments() {
+        let input = "c a comment\np cnf 3 2\n1 -2\n  3 0\nc between\n-1 0\n";
+        assert_eq!(parse_dimacs(input), Ok((clauses(&[&[1, -2, 3], &[-1]]), 3)));
+    }
+
+    #[test]
+    fn rejects_truncated_header() {
+        let err = parse_dimacs("c header\np cnf\n1 0\n").unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!(err.message.contains("p cnf"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_non_integer_literal() {
+        let err = parse_dimacs("p cnf 2 1\n1 x 0\n").unwrap_err();
+        assert_eq!(err, ParseError::new(2, "expected integer, found 'x'"));
+    }
+
+    #[test]
+    fn rejects_missing_trailing_zero() {
+        let err = parse_dimacs("p cnf 2 2\n1 2 0\n-1 -2\n").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert!(err.message.contains("terminated"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_missing_header() {
+        assert!(parse_dimacs("1 2 0\n").is_err());
+        assert!(parse_dimacs("c only comments\n").is_err());
+    }
+
+    #[test]
+    fn keeps_empty_clauses() {
+        let (formula, num_vars) = parse_dimacs("p cnf 2 3\n1 2 0\n0\n-1 0\n").unwrap();
+        assert_eq!(formula, clauses(&[&[1, 2], &[], &[-1]]));
+        assert!(!crate::Solver::new(formula, num_vars).solve());
+    }
+
+    #[test]
+    fn percent_line_ends_the_formula() {
+        let input = "p cnf 3 2\n1 -3 0\n2 3 -1 0\n%\n0\n\n";
+        assert_eq!(parse_dimacs(input), Ok((clauses(&[&[1, -3], &[2, 3, -1]]), 3)));
+    }
+
+    #[test]
+    fn write_dimacs_round_trips() {
+        let formulas: [(Vec<Clause>, usize); 4] = [
+            (vec![], 0),
+            (clauses(&[&[1, -2, 3], &[-1], &[2, 3]]), 5),
+            (clauses(&[&[1, 2], &[], &[-2]]), 2),
+            ((1..=30).map(|var| vec![Lit::from(var), Lit::from(var - 31)]).collect(), 30),
+        ];
+        for (formula, num_vars) in formulas {
+            let mut out = Vec::new();
+            write_dimacs(&formula, num_vars, &mut out).unwrap();
+            let text = String::from_utf8(out).unwrap();
+            let parsed = parse_dimacs(&text).unwrap();
+            assert_eq!(parsed, (formula, num_vars));
+
+            let mut again = Vec::new();
+            write_dimacs(&parsed.0, parsed.1, &mut again).unwrap();
+            assert_eq!(String::from_utf8(again).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn understated_header_grows_num_vars() {
+        let dimacs = Dimacs::parse("p cnf 2 2\n1 -2 0\n2 5 0\n").unwrap();
+        assert_eq!(dimacs.num_vars, 5);
+        assert_eq!(dimacs.declared_vars, 2);
+        let mut solver = crate::Solver::new(dimacs.clauses, dimacs.num_vars);
+        assert!(solver.solve());
+        assert_eq!(solver.model().unwrap().len(), 6);
+
+        let dimacs = Dimacs::parse("p cnf 9 1\n1 -2 0\n").unwrap();
+        assert_eq!((dimacs.num_vars, dimacs.declared_vars), (9, 9));
+    }
+
+    #[test]
+    fn solver_outgrows_an_understated_count() {
+        // the header's count as is, fed straight to the solver
+        let dimacs = Dimacs::parse("p cnf 3 3\n1 -5 0\n5 4 0\n-4 -1 0\n").unwrap();
+        let mut solver = crate::Solver::new(dimacs.clauses.clone(), dimacs.declared_vars);
+        assert_eq!(solver.num_vars, 5);
+        assert!(solver.solve());
+        let model = solver.model().unwrap();
+        assert_eq!(model.len(), 6);
+        assert!(crate::check_model(&dimacs.clauses, &model));
+    }
+
+    #[test]
+    fn rejects_literals_out_of_integer_range() {
+        #[cfg(not(feature = "i64"))]
+        {
+            let err = Dimacs::parse("p cnf 2 1\n1 3000000000 0\n").unwrap_err();
+            assert_eq!(err, ParseError::new(2, "expected integer, found '3000000000'"));
+        }
+        let err = Dimacs::parse("p cnf 2 1\n1 10000000000000000000 0\n").unwrap_err();
+        assert_eq!(err, ParseError::new(2, "expected integer, found '10000000000000000000'"));
+    }
+
+    #[test]
+    fn rejects_a_literal_without_a_negation() {
+        let min = LitInt::MIN.to_string();
+        let message =
+            format!("variable out of range in '{}', the largest allowed is {}", min, MAX_VAR);
+        let err = Dimacs::parse(&format!("p cnf 2 1\n1 {} 0\n", min)).unwrap_err();
+        assert_eq!(err, ParseError::new(2, message.clone()));
+        let err = parse_assumptions(&format!("1\n{} 0\n", min)).unwrap_err();
+        assert_eq!(err, ParseError::new(2, message));
+
+        let too_many = (MAX_VAR + 1).to_string();
+        let err = Dimacs::parse(&format!("p cnf {} 1\n1 0\n", too_many)).unwrap_err();
+        assert_eq!(err.line, 1);
+    }
+
+    #[test]
+    fn huge_header_counts_only_the_variables_in_use() {
+        let input = format!("p cnf {} 1\n1 -2 0\n", MAX_VAR);
+        let dimacs = Dimacs::parse(&input).unwrap();
+        assert_eq!((dimacs.num_vars, dimacs.declared_vars), (input.len(), MAX_VAR));
+        let dimacs = Dimacs::parse(&format!("p cnf {} 1\n1 -{} 0\n", MAX_VAR, MAX_VAR)).unwrap();
+        assert_eq!(dimacs.num_vars, MAX_VAR);
+    }
+
+    #[test]
+    fn assumptions_end_at_the_first_zero() {
+        let lits = parse_assumptions("c query\n3\n-5 0\n7 0\n").unwrap();
+        assert_eq!(lits, [Lit::from(3), Lit::from(-5)]);
+        assert_eq!(parse_assumptions("0\n").unwrap(), []);
+        let err = parse_assumptions("1 -2\n").unwrap_err();
+        assert_eq!(err, ParseError::new(1, "literals are not terminated by 0"));
+    }
+
+    #[cfg(feature = "i64")]
+    #[test]
+    fn i64_literals_go_beyond_i32() {
+        let dimacs = Dimacs::parse("p cnf 2 1\n1 -3000000000 0\n").unwrap();
+        assert_eq!(dimacs.clauses[0][1], Lit::new(3_000_000_000, false));
+        assert_eq!(dimacs.num_vars, 3_000_000_000);
+    }
+
+    #[test]
+    fn clause_spans_lines_around_a_comment() {
+        let input = "p cnf 4 2\n1\n-2\nc wedged in the clause\n3 0 4\n0\n";
+        let dimacs = Dimacs::parse(input).unwrap();
+        assert_eq!(dimacs.clauses, clauses(&[&[1, -2, 3], &[4]]));
+        // errors still name the line the token is on
+        let err = Dimacs::parse("p cnf 2 1\n1\nc note\n2 y 0\n").unwrap_err();
+        assert_eq!(err.line, 4);
+    }
+
+    #[test]
+    fn x_lines_are_xor_constraints() {
+        let dimacs = Dimacs::parse("p cnf 3 3\nx 1 -2 0\n1 3 0\nx3\n2 0\n").unwrap();
+        assert_eq!(dimacs.clauses, clauses(&[&[1, 3]]));
+        assert_eq!(dimacs.xors, clauses(&[&[1, -2], &[3, 2]]));
+        let err = Dimacs::parse("p cnf 2 1\nx 1 2\n").unwrap_err();
+        assert!(err.message.contains("not terminated"), "{}", err);
+    }
+
+    #[test]
+    fn clause_count_mismatches_fail_only_when_strict() {
+        // the header promises 3 clauses: one too many, then one too few
+        for input in ["p cnf 2 3\n1 0\n-2 0\n", "p cnf 2 3\n1 0\n-2 0\n1 2 0\n2 0\n"] {
+            let dimacs = Dimacs::parse(input).unwrap();
+            assert_eq!(dimacs.declared_clauses, Some(3));
+            assert_ne!(dimacs.clause_count(), 3);
+            let err = Dimacs::parse_strict(input).unwrap_err();
+            assert!(err.message.contains("declares 3 clauses"), "{}", err);
+        }
+        let input = "p cnf 2 3\n1 0\nx 1 2 0\n-2 0\n";
+        assert_eq!(Dimacs::parse_strict(input).unwrap().clause_count(), 3);
+    }
+
+    #[test]
+    fn only_c_and_whitespace_starts_a_comment() {
+        let input = "  c indented\n\tp cnf 2 1\nc\nc\ttab\n  1 -2 0\n";
+        assert_eq!(Dimacs::parse(input).unwrap().clauses, clauses(&[&[1, -2]]));
+
+        let err = Dimacs::parse("p cnf 2 1\ncnf_extra 1\n1 0\n").unwrap_err();
+        assert_eq!(err, ParseError::new(2, "expected integer, found 'cnf_extra'"));
+        let err = Dimacs::parse("pcnf 2 1\n1 0\n").unwrap_err();
+        assert_eq!(err.line, 1);
+    }
+
+    #[test]
+    fn c_ind_lines_give_the_independent_support() {
+        let input = "c ind 3 1 0\nc a plain comment 7 0\np cnf 5 2\nc ind 5\nc ind 2 0\n\
+                     1 -2 0\nc index 4 0\n3 5 0\n";
+        let dimacs = Dimacs::parse(input).unwrap();
+        assert_eq!(dimacs.independent_support, vec![3, 1, 5, 2]);
+        assert_eq!(dimacs.clauses, crate::testing::clauses(&[&[1, -2], &[3, 5]]));
+        assert!(Dimacs::parse("p cnf 1 1\n1 0\n").unwrap().independent_support.is_empty());
+
+        let err = Dimacs::parse("p cnf 2 1\nc ind 1 -2 0\n1 0\n").unwrap_err();
+        assert_eq!(err.line, 2);
+    }
+}
@@ -0,0 +1,140 @@
+// GraphViz dumps of the implication graph at a conflict, for looking at what
+// conflict analysis works with.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::{Lit, Solver, Var};
+
+impl Solver {
+    /// Writes the implication graph at the next conflict of a solve to `out`
+    /// in GraphViz dot format, then goes on solving as usual. The graph has
+    /// the assignments conflict analysis could reach from the conflicting
+    /// clause: decisions as boxes, implied literals with the reason clause
+    /// that forced them, and a node for the conflict itself. Only one graph
+    /// is written; see `finish_conflict_dump` for whether it was.
+    pub fn set_conflict_dump(&mut self, out: Box<dyn Write>) {
+        self.conflict_dump = Some(out);
+        self.conflict_dump_result = None;
+    }
+
+    /// Whether the graph asked for with `set_conflict_dump` was written:
+    /// `Ok(false)` if no conflict has happened since, in which case nothing
+    /// will be written any more, or the error hit while writing it.
+    pub fn finish_conflict_dump(&mut self) -> io::Result<bool> {
+        self.conflict_dump = None;
+        match self.conflict_dump_result.take() {
+            Some(result) => result.map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    // Writes the implication graph behind clause `conflict`, which the current
+    // assignment falsifies, and drops the sink.
+    pub(crate) fn dump_conflict(&mut self, conflict: usize) {
+        let Some(mut out) = self.conflict_dump.take() else {
+            return;
+        };
+        let graph = self.conflict_graph(conflict);
+        let result = out.write_all(graph.as_bytes()).and_then(|()| out.flush());
+        self.conflict_dump_result = Some(result);
+    }
+
+    fn conflict_graph(&self, conflict: usize) -> String {
+        let clause_label = |lits: &[Lit]| {
+            let lits: Vec<String> = lits.iter().map(|lit| lit.to_string()).collect();
+            format!("({})", lits.join(" "))
+        };
+
+        let mut graph = String::from("digraph conflict {\n");
+        let conflict_lits = &self.clauses[conflict];
+        let _ = writeln!(
+            graph,
+            "  conflict [shape=octagon, label=\"conflict\\nc{} {}\"];",
+            conflict,
+            clause_label(conflict_lits)
+        );
+        let mut stack: Vec<usize> = conflict_lits.iter().map(|lit| lit.var() as usize).collect();
+        for &var in &stack {
+            let _ = writeln!(graph, "  v{} -> conflict;", var);
+        }
+
+        // every assigned variable the conflict depends on, once each
+        let mut visited = vec![false; self.num_vars + 1];
+        while let Some(var) = stack.pop() {
+            if visited[var] {
+                continue;
+            }
+            visited[var] = true;
+            let value = self.assignment.get(var).expect("unassigned literal in the graph");
+            let lit = Lit::new(var as Var, value);
+            let level = self.levels[var];
+            let attrs = match self.reasons[var] {
+                Some(reason) => {
+                    let lits = &self.clauses[reason];
+                    for other in lits.iter().map(|lit| lit.var() as usize).filter(|&v| v != var) {
+                        let _ = writeln!(graph, "  v{} -> v{} [label=c{}];", other, var, reason);
+                        stack.push(other);
+                    }
+                    format!("label=\"{} @{}\\nc{} {}\"", lit, level, reason, clause_label(lits))
+                }
+                // root-level seeds have no reason but aren't decisions
+                None if level == 0 => format!("shape=box, style=dashed, label=\"{} @0\"", lit),
+                None => format!("shape=box, label=\"{} @{}\"", lit, level),
+            };
+            let _ = writeln!(graph, "  v{} [{}];", var, attrs);
+        }
+        graph.push_str("}\n");
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{pigeonhole, SharedBuf};
+    use crate::{Lit, LitInt, Solver};
+
+    #[test]
+    fn graph_has_the_conflict_and_a_decision() {
+        let (formula, num_vars) = pigeonhole(3);
+        let mut solver = Solver::new_raw(formula.clone(), num_vars);
+        let out = SharedBuf::default();
+        solver.set_conflict_dump(Box::new(out.clone()));
+        assert!(!solver.solve());
+        assert!(solver.finish_conflict_dump().unwrap());
+
+        let graph = out.contents();
+        assert!(graph.starts_with("digraph conflict {\n") && graph.ends_with("}\n"));
+        let conflict = graph
+            .lines()
+            .find(|line| line.starts_with("  conflict [shape=octagon"))
+            .expect("no conflict node");
+        // the node is labelled with a clause of the formula, and each of its
+        // variables points at it
+        let (start, end) = (conflict.find('(').unwrap(), conflict.find(')').unwrap());
+        let mut lits: Vec<Lit> = conflict[start + 1..end]
+            .split(' ')
+            .map(|lit| Lit::from(lit.parse::<LitInt>().unwrap()))
+            .collect();
+        for lit in &lits {
+            assert!(graph.contains(&format!("  v{} -> conflict;\n", lit.var())));
+        }
+        lits.sort();
+        assert!(formula.iter().any(|clause| {
+            let mut clause = clause.clone();
+            clause.sort();
+            clause == lits
+        }));
+        assert!(graph.lines().any(|line| line.contains("[shape=box, label=")), "{}", graph);
+    }
+
+    #[test]
+    fn nothing_is_written_without_a_conflict() {
+        let mut solver = Solver::new_raw(Vec::new(), 2);
+        let out = SharedBuf::default();
+        solver.set_conflict_dump(Box::new(out.clone()));
+        assert!(solver.solve());
+        assert!(!solver.finish_conflict_dump().unwrap());
+        assert_eq!(out.contents(), "");
+    }
+}
@@ -0,0 +1,107 @@
+// Clausal encodings of cardinality constraints, written into a CnfBuilder.
+
+use crate::{CnfBuilder, Lit};
+
+// Up to this many literals the pairwise encoding of at-most-one is used; its
+// n(n-1)/2 binary clauses beat the counter's auxiliary variables.
+const PAIRWISE_LIMIT: usize = 6;
+
+/// Adds clauses allowing at most one of `lits` to be true: pairwise
+/// exclusions for a few literals, a sequential counter for more.
+pub fn encode_at_most_one(builder: &mut CnfBuilder, lits: &[Lit]) {
+    if lits.len() > PAIRWISE_LIMIT {
+        encode_at_most_k(builder, lits, 1);
+        return;
+    }
+    for (i, &a) in lits.iter().enumerate() {
+        for &b in &lits[i + 1..] {
+            builder.add_clause([-a, -b]);
+        }
+    }
+}
+
+/// Adds clauses allowing at most `k` of `lits` to be true, using Sinz's
+/// sequential counter: register `s[i][j]` is forced true once more than `j`
+/// of the first `i + 1` literals are, and a literal that would push the count
+/// past `k` is forbidden. Takes (n - 1) * k auxiliary variables from the
+/// builder and about 2nk clauses.
+pub fn encode_at_most_k(builder: &mut CnfBuilder, lits: &[Lit], k: usize) {
+    let n = lits.len();
+    if k >= n {
+        return;
+    }
+    if k == 0 {
+        for &lit in lits {
+            builder.add_clause([-lit]);
+        }
+        return;
+    }
+
+    let registers: Vec<Vec<Lit>> = (0..n - 1)
+        .map(|_| (0..k).map(|_| builder.fresh_var()).collect())
+        .collect();
+
+    builder.add_clause([-lits[0], registers[0][0]]);
+    for &s in &registers[0][1..] {
+        builder.add_clause([-s]);
+    }
+    for i in 1..n - 1 {
+        let (prev, cur) = (&registers[i - 1], &registers[i]);
+        builder.add_clause([-lits[i], cur[0]]);
+        builder.add_clause([-prev[0], cur[0]]);
+        for j in 1..k {
+            builder.add_clause([-lits[i], -prev[j - 1], cur[j]]);
+            builder.add_clause([-prev[j], cur[j]]);
+        }
+        builder.add_clause([-lits[i], -prev[k - 1]]);
+    }
+    builder.add_clause([-lits[n - 1], -registers[n - 2][k - 1]]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SolveResult;
+
+    // Checks the encoding of "at most k of n literals" against the count of
+    // true literals, for every assignment to them. Some of the literals are
+    // negative, so the encoding mustn't rely on polarity.
+    fn check_against_count(n: usize, k: usize, encode: impl Fn(&mut CnfBuilder, &[Lit])) {
+        let mut builder = CnfBuilder::new();
+        let lits: Vec<Lit> = (0..n)
+            .map(|i| if i % 3 == 1 { -builder.fresh_var() } else { builder.fresh_var() })
+            .collect();
+        encode(&mut builder, &lits);
+        let mut solver = builder.build();
+        for bits in 0u32..1 << n {
+            let assumed: Vec<Lit> = lits
+                .iter()
+                .enumerate()
+                .map(|(i, &lit)| if bits >> i & 1 == 1 { lit } else { -lit })
+                .collect();
+            let expected = if bits.count_ones() as usize <= k {
+                SolveResult::Sat
+            } else {
+                SolveResult::Unsat
+            };
+            assert_eq!(solver.solve_under_assumptions(&assumed), expected, "n {n} k {k} {bits:b}");
+        }
+    }
+
+    #[test]
+    fn at_most_k_matches_brute_force_count() {
+        for n in 0..=7 {
+            for k in 0..=n + 1 {
+                check_against_count(n, k, |builder, lits| encode_at_most_k(builder, lits, k));
+            }
+        }
+    }
+
+    #[test]
+    fn at_most_one_matches_brute_force_count() {
+        // past six literals the sequential counter takes over
+        for n in 0..=9 {
+            check_against_count(n, 1, encode_at_most_one);
+        }
+    }
+}
@@ -0,0 +1,428 @@
+// A small gzip (RFC 1952) / deflate (RFC 1951) decoder, enough to read the
+// .cnf.gz files benchmark suites ship without pulling in a dependency. It
+// works on whole buffers and favours simplicity over speed.
+
+use std::io;
+
+const MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+// base values and extra bits of the length codes 257..285 and distance codes
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115,
+    131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// order in which a dynamic block lists its code length code lengths
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("gzip: {}", message))
+}
+
+/// Returns true if `data` starts with the gzip magic bytes.
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+/// Decompresses a gzip stream, including one made of several concatenated
+/// members, checking each member's CRC and length.
+pub fn gunzip(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    loop {
+        pos = member(data, pos, &mut out)?;
+        // trailing zero padding after the last member is tolerated
+        if data[pos..].iter().all(|&b| b == 0) {
+            return Ok(out);
+        }
+    }
+}
+
+// Decodes the member starting at `pos` onto `out` and returns where it ends.
+fn member(data: &[u8], mut pos: usize, out: &mut Vec<u8>) -> io::Result<usize> {
+    let header = data.get(pos..pos + 10).ok_or_else(|| invalid("truncated header"))?;
+    if header[..2] != MAGIC {
+        return Err(invalid("bad magic number"));
+    }
+    if header[2] != 8 {
+        return Err(invalid("unsupported compression method"));
+    }
+    let flags = header[3];
+    pos += 10;
+
+    if flags & FEXTRA != 0 {
+        let len = data.get(pos..pos + 2).ok_or_else(|| invalid("truncated header"))?;
+        pos += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let len = data
+                .get(pos..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+                .ok_or_else(|| invalid("truncated header"))?;
+            pos += len + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+    if pos > data.len() {
+        return Err(invalid("truncated header"));
+    }
+
+    let start = out.len();
+    let mut input = Bits::new(data, pos);
+    inflate(&mut input, out)?;
+    pos = input.pos;
+
+    let trailer = data.get(pos..pos + 8).ok_or_else(|| invalid("truncated trailer"))?;
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    if crc32(&out[start..]) != crc {
+        return Err(invalid("CRC mismatch"));
+    }
+    if (out.len() - start) as u32 != size {
+        return Err(invalid("length mismatch"));
+    }
+    Ok(pos + 8)
+}
+
+// Little-endian bit reader over a byte slice, as deflate packs its codes.
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    count: u32,
+}
+
+impl<'a> Bits<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Bits {
+            data,
+            pos,
+            buf: 0,
+            count: 0,
+        }
+    }
+
+    fn bits(&mut self, n: u32) -> io::Result<u32> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or_else(|| invalid("unexpected end of data"))?;
+            self.buf |= (byte as u32) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+        let value = self.buf & ((1u32 << n) - 1);
+        self.buf >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    // Drops the bits left in the current byte; fewer than 8 are ever buffered.
+    fn align(&mut self) {
+        self.buf = 0;
+        self.count = 0;
+    }
+}
+
+// Canonical Huffman code, decoded one bit at a time: `counts[len]` codes
+// have length `len`, and `symbols` lists the symbols in code order.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> io::Result<Self> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        // every length may hold at most as many codes as are left over
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = 2 * left - count as i32;
+            if left < 0 {
+                return Err(invalid("over-subscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, input: &mut Bits) -> io::Result<u16> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for &count in &self.counts[1..] {
+            code |= input.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("invalid Huffman code"))
+    }
+}
+
+fn inflate(input: &mut Bits, out: &mut Vec<u8>) -> io::Result<()> {
+    loop {
+        let last = input.bits(1)? == 1;
+        match input.bits(2)? {
+            0 => stored(input, out)?,
+            1 => {
+                let (lengths, distances) = fixed_codes()?;
+                codes(input, out, &lengths, &distances)?;
+            }
+            2 => {
+                let (lengths, distances) = dynamic_codes(input)?;
+                codes(input, out, &lengths, &distances)?;
+            }
+            _ => return Err(invalid("invalid block type")),
+        }
+        if last {
+            input.align();
+            return Ok(());
+        }
+    }
+}
+
+fn stored(input: &mut Bits, out: &mut Vec<u8>) -> io::Result<()> {
+    input.align();
+    let pos = input.pos;
+    let header = input.data.get(pos..pos + 4).ok_or_else(|| invalid("truncated block"))?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let nlen = u16::from_le_bytes([header[2], header[3]]);
+    if len != !nlen {
+        return Err(invalid("corrupt stored block length"));
+    }
+    let block = input
+        .data
+        .get(pos + 4..pos + 4 + len as usize)
+        .ok_or_else(|| invalid("truncated block"))?;
+    out.extend_from_slice(block);
+    input.pos = pos + 4 + len as usize;
+    Ok(())
+}
+
+fn fixed_codes() -> io::Result<(Huffman, Huffman)> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes(input: &mut Bits) -> io::Result<(Huffman, Huffman)> {
+    let num_lengths = input.bits(5)? as usize + 257;
+    let num_distances = input.bits(5)? as usize + 1;
+    let num_code_lengths = input.bits(4)? as usize + 4;
+    if num_lengths > 286 || num_distances > 30 {
+        return Err(invalid("too many length or distance codes"));
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &slot in &CODE_LENGTH_ORDER[..num_code_lengths] {
+        code_lengths[slot] = input.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; num_lengths + num_distances];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code_length_code.decode(input)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *i
+                    .checked_sub(1)
+                    .and_then(|p| lengths.get(p))
+                    .ok_or_else(|| invalid("repeat with no previous length"))?;
+                (previous, 3 + input.bits(2)? as usize)
+            }
+            17 => (0, 3 + input.bits(3)? as usize),
+            _ => (0, 11 + input.bits(7)? as usize),
+        };
+        if i + repeat > lengths.len() {
+            return Err(invalid("too many code lengths"));
+        }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(invalid("missing end-of-block code"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..num_lengths])?,
+        Huffman::new(&lengths[num_lengths..])?,
+    ))
+}
+
+// Decodes the literal/length and distance codes of one compressed block.
+fn codes(
+    input: &mut Bits,
+    out: &mut Vec<u8>,
+    lengths: &Huffman,
+    distances: &Huffman,
+) -> io::Result<()> {
+    loop {
+        let symbol = lengths.decode(input)? as usize;
+        if symbol < 256 {
+            out.push(symbol as u8);
+            continue;
+        }
+        if symbol == 256 {
+            return Ok(());
+        }
+
+        let symbol = symbol - 257;
+        if symbol >= LENGTH_BASE.len() {
+            return Err(invalid("invalid length code"));
+        }
+        let len = LENGTH_BASE[symbol] as usize + input.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+        let symbol = distances.decode(input)? as usize;
+        if symbol >= DIST_BASE.len() {
+            return Err(invalid("invalid distance code"));
+        }
+        let dist = DIST_BASE[symbol] as usize + input.bits(DIST_EXTRA[symbol] as u32)? as usize;
+        if dist > out.len() {
+            return Err(invalid("distance too far back"));
+        }
+        // the copy may overlap what it produces, so go byte by byte
+        let from = out.len() - dist;
+        for k in 0..len {
+            out.push(out[from + k]);
+        }
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 { 0xedb8_8320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *entry = c;
+    }
+
+    let mut crc = !0u32;
+    for &byte in data {
+        crc = table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_dimacs, Solver};
+
+    // three pigeons in two holes, unsatisfiable
+    const PLAIN: &[u8] = b"p cnf 6 9\n1 2 0\n3 4 0\n5 6 0\n-1 -3 0\n-1 -5 0\n-3 -5 0\n\
+                           -2 -4 0\n-2 -6 0\n-4 -6 0\n";
+
+    // PLAIN as a single stored block
+    const STORED: [u8; 99] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x03, 0x01, 0x4c,
+        0x00, 0xb3, 0xff, 0x70, 0x20, 0x63, 0x6e, 0x66, 0x20, 0x36, 0x20, 0x39,
+        0x0a, 0x31, 0x20, 0x32, 0x20, 0x30, 0x0a, 0x33, 0x20, 0x34, 0x20, 0x30,
+        0x0a, 0x35, 0x20, 0x36, 0x20, 0x30, 0x0a, 0x2d, 0x31, 0x20, 0x2d, 0x33,
+        0x20, 0x30, 0x0a, 0x2d, 0x31, 0x20, 0x2d, 0x35, 0x20, 0x30, 0x0a, 0x2d,
+        0x33, 0x20, 0x2d, 0x35, 0x20, 0x30, 0x0a, 0x2d, 0x32, 0x20, 0x2d, 0x34,
+        0x20, 0x30, 0x0a, 0x2d, 0x32, 0x20, 0x2d, 0x36, 0x20, 0x30, 0x0a, 0x2d,
+        0x34, 0x20, 0x2d, 0x36, 0x20, 0x30, 0x0a, 0x11, 0x97, 0x5d, 0xfc, 0x4c,
+        0x00, 0x00, 0x00,
+    ];
+    // PLAIN with the fixed Huffman codes
+    const FIXED: [u8; 67] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x03, 0x2b, 0x50,
+        0x48, 0xce, 0x4b, 0x53, 0x30, 0x53, 0xb0, 0xe4, 0x32, 0x54, 0x30, 0x52,
+        0x30, 0xe0, 0x32, 0x56, 0x30, 0x01, 0x92, 0xa6, 0x40, 0x11, 0x03, 0x2e,
+        0x5d, 0x43, 0x05, 0x5d, 0x63, 0x28, 0x6d, 0x0a, 0xa2, 0x8d, 0xa1, 0xb4,
+        0x91, 0x82, 0xae, 0x09, 0x94, 0x06, 0xab, 0x33, 0x81, 0xd0, 0x00, 0x11,
+        0x97, 0x5d, 0xfc, 0x4c, 0x00, 0x00, 0x00,
+    ];
+    // PLAIN with its own Huffman codes
+    const DYNAMIC: [u8; 76] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x05, 0xc1,
+        0xb1, 0x09, 0xc0, 0x30, 0x10, 0x04, 0xc1, 0xfc, 0xaa, 0xd8, 0x06, 0x0e,
+        0xee, 0x5f, 0xd2, 0x83, 0xeb, 0x31, 0x38, 0x34, 0xee, 0x3f, 0xf2, 0xcc,
+        0xc7, 0xfd, 0x3e, 0x0c, 0x97, 0x8a, 0x26, 0x5a, 0x6c, 0xa2, 0xc3, 0x10,
+        0xb9, 0xf0, 0x22, 0x72, 0xe1, 0x43, 0xe4, 0x85, 0x0f, 0x91, 0x1b, 0x6f,
+        0x22, 0x37, 0x1e, 0x22, 0x6f, 0x3c, 0x44, 0x3f, 0x11, 0x97, 0x5d, 0xfc,
+        0x4c, 0x00, 0x00, 0x00,
+    ];
+
+    fn solve(text: &[u8]) -> bool {
+        let (clauses, num_vars) = parse_dimacs(std::str::from_utf8(text).unwrap()).unwrap();
+        Solver::new(clauses, num_vars).solve()
+    }
+
+    #[test]
+    fn each_block_type_round_trips() {
+        for data in [&STORED[..], &FIXED, &DYNAMIC] {
+            assert!(is_gzip(data));
+            let text = gunzip(data).unwrap();
+            assert_eq!(text, PLAIN);
+            assert_eq!(solve(&text), solve(PLAIN));
+        }
+        assert!(!is_gzip(PLAIN));
+    }
+
+    #[test]
+    fn members_are_concatenated() {
+        let mut data = [&STORED[..], &FIXED, &DYNAMIC].concat();
+        data.extend_from_slice(&[0; 4]);
+        assert_eq!(gunzip(&data).unwrap(), PLAIN.repeat(3));
+    }
+
+    #[test]
+    fn corruption_is_detected() {
+        for data in [&STORED[..], &FIXED, &DYNAMIC] {
+            let mut bad = data.to_vec();
+            let crc = bad.len() - 8;
+            bad[crc] ^= 1;
+            let error = gunzip(&bad).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(error.to_string().contains("CRC mismatch"));
+
+            for len in 0..data.len() {
+                assert!(gunzip(&data[..len]).is_err(), "prefix of {} bytes", len);
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+// Binary max-heap of variables ordered by activity, with a position index so
+// membership tests and priority increases are O(1) / O(log n).
+pub(crate) struct VarHeap {
+    heap: Vec<usize>,
+    // position of each variable in `heap`, usize::MAX when absent
+    indices: Vec<usize>,
+}
+
+impl VarHeap {
+    pub(crate) fn new(num_vars: usize) -> Self {
+        VarHeap {
+            heap: Vec::with_capacity(num_vars),
+            indices: vec![usize::MAX; num_vars + 1],
+        }
+    }
+
+    // Makes room for variables up to `num_vars`, which start out absent.
+    pub(crate) fn grow(&mut self, num_vars: usize) {
+        if self.indices.len() < num_vars + 1 {
+            self.indices.resize(num_vars + 1, usize::MAX);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn contains(&self, var: usize) -> bool {
+        self.indices[var] != usize::MAX
+    }
+
+    pub(crate) fn insert(&mut self, var: usize, activity: &[f64]) {
+        if self.contains(var) {
+            return;
+        }
+        self.indices[var] = self.heap.len();
+        self.heap.push(var);
+        self.sift_up(self.heap.len() - 1, activity);
+    }
+
+    // Restores the heap order after `var`'s activity was increased.
+    pub(crate) fn increased(&mut self, var: usize, activity: &[f64]) {
+        if self.contains(var) {
+            self.sift_up(self.indices[var], activity);
+        }
+    }
+
+    pub(crate) fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+        let top = *self.heap.first()?;
+        let last = self.heap.pop().unwrap();
+        self.indices[top] = usize::MAX;
+        if !self.heap.is_empty() {
+            self.heap[0] = last;
+            self.indices[last] = 0;
+            self.sift_down(0, activity);
+        }
+        Some(top)
+    }
+
+    fn sift_up(&mut self, mut pos: usize, activity: &[f64]) {
+        let var = self.heap[pos];
+        while pos > 0 {
+            let parent = (pos - 1) / 2;
+            if activity[self.heap[parent]] >= activity[var] {
+                break;
+            }
+            self.heap[pos] = self.heap[parent];
+            self.indices[self.heap[pos]] = pos;
+            pos = parent;
+        }
+        self.heap[pos] = var;
+        self.indices[var] = pos;
+    }
+
+    fn sift_down(&mut self, mut pos: usize, activity: &[f64]) {
+        let var = self.heap[pos];
+        loop {
+            let left = 2 * pos + 1;
+            if left >= self.heap.len() {
+                break;
+            }
+            let right = left + 1;
+            let child = if right < self.heap.len()
+                && activity[self.heap[right]] > activity[self.heap[left]]
+            {
+                right
+            } else {
+                left
+            };
+            if activity[self.heap[child]] <= activity[var] {
+                break;
+            }
+            self.heap[pos] = self.heap[child];
+            self.indices[self.heap[pos]] = pos;
+            pos = child;
+        }
+        self.heap[pos] = var;
+        self.indices[var] = pos;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pops_in_activity_order() {
+        let mut activity = vec![0.0, 3.0, 1.0, 4.0, 1.5, 9.0, 2.0];
+        let mut heap = VarHeap::new(6);
+        for var in 1..=6 {
+            heap.insert(var, &activity);
+        }
+        activity[2] = 10.0;
+        heap.increased(2, &activity);
+        assert_eq!(heap.pop(&activity), Some(2));
+        assert!(!heap.contains(2));
+
+        heap.grow(7);
+        activity.push(3.5);
+        heap.insert(7, &activity);
+        let order: Vec<usize> = std::iter::from_fn(|| heap.pop(&activity)).collect();
+        assert_eq!(order, [5, 3, 7, 1, 6, 4]);
+    }
+}
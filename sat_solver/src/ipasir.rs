@@ -0,0 +1,131 @@
+// An IPASIR-style incremental interface over Solver, for tools that feed
+// clauses and assumptions one DIMACS literal at a time.
+
+use std::mem;
+
+use crate::{Clause, Lit, LitInt, SolveResult, Solver};
+
+/// Incremental front end in the style of the IPASIR API: clauses are built
+/// up literal by literal with `add`, each ended by a 0, `assume` queues
+/// literals assumed true for the next `solve` only, and everything added or
+/// learned is kept between solves. Variables need not be declared; any
+/// literal can mention a new one.
+pub struct IncrementalSolver {
+    solver: Solver,
+    clause: Clause,
+    assumptions: Vec<Lit>,
+}
+
+impl IncrementalSolver {
+    pub fn new() -> Self {
+        IncrementalSolver {
+            solver: Solver::new(Vec::new(), 0),
+            clause: Vec::new(),
+            assumptions: Vec::new(),
+        }
+    }
+
+    /// Adds `lit` to the clause being built, or adds that clause to the
+    /// formula if `lit` is 0. A clause left unterminated when `solve` is
+    /// called is not part of that solve.
+    pub fn add(&mut self, lit: LitInt) {
+        if lit == 0 {
+            self.solver.add_clause(mem::take(&mut self.clause));
+        } else {
+            self.clause.push(Lit::from(lit));
+        }
+    }
+
+    /// Assumes `lit` true for the next `solve`.
+    pub fn assume(&mut self, lit: LitInt) {
+        self.assumptions.push(Lit::from(lit));
+    }
+
+    /// Solves the formula under the queued assumptions, which are dropped
+    /// afterwards. `Unknown` only comes back when an interrupt set on
+    /// `solver_mut()` stops the search.
+    pub fn solve(&mut self) -> SolveResult {
+        let assumptions = mem::take(&mut self.assumptions);
+        self.solver.solve_under_assumptions(&assumptions)
+    }
+
+    /// Value of `lit` in the model of the last `solve`, None if it wasn't
+    /// `Sat`. Variables no clause mentions are reported as true.
+    pub fn val(&self, lit: LitInt) -> Option<bool> {
+        if self.solver.last_result != SolveResult::Sat {
+            return None;
+        }
+        let lit = Lit::from(lit);
+        let value = self.solver.model.get(lit.var() as usize).copied().unwrap_or(true);
+        Some(value == lit.is_positive())
+    }
+
+    /// Whether the assumption `lit` is part of the reason the last `solve`
+    /// returned `Unsat`, see `Solver::unsat_core`.
+    pub fn failed(&self, lit: LitInt) -> bool {
+        self.solver.core.contains(&Lit::from(lit))
+    }
+
+    pub fn solver(&self) -> &Solver {
+        &self.solver
+    }
+
+    /// The underlying solver, e.g. to set an interrupt or a proof sink.
+    pub fn solver_mut(&mut self) -> &mut Solver {
+        &mut self.solver
+    }
+}
+
+impl Default for IncrementalSolver {
+    fn default() -> Self {
+        IncrementalSolver::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A CEGAR-style loop: find a model of the abstraction, refute it as a
+    // counterexample with a blocking clause, and solve again, until the
+    // abstraction has no models left. Each round also checks one assumption.
+    #[test]
+    fn blocking_loop_enumerates_every_model() {
+        let mut s = IncrementalSolver::new();
+        for lit in [1, 2, 3, 0, -1, -2, 0] {
+            s.add(lit);
+        }
+        let mut found = 0;
+        while s.solve() == SolveResult::Sat {
+            let model: Vec<LitInt> = (1..=3)
+                .map(|v| if s.val(v) == Some(true) { v } else { -v })
+                .collect();
+            assert!(model.contains(&1) || model.contains(&2) || model.contains(&3));
+            assert!(model.contains(&-1) || model.contains(&-2));
+            for &lit in &model {
+                s.add(-lit);
+            }
+            s.add(0);
+            found += 1;
+            assert!(found <= 5);
+        }
+        assert_eq!(found, 5);
+        assert_eq!(s.val(1), None);
+    }
+
+    #[test]
+    fn assumptions_hold_for_one_solve() {
+        let mut s = IncrementalSolver::new();
+        for lit in [1, 2, 0, -1, 0] {
+            s.add(lit);
+        }
+        s.assume(-2);
+        s.assume(4);
+        assert_eq!(s.solve(), SolveResult::Unsat);
+        assert!(s.failed(-2));
+        assert!(!s.failed(4));
+        assert_eq!(s.solve(), SolveResult::Sat);
+        assert_eq!(s.val(2), Some(true));
+        assert_eq!(s.val(-1), Some(true));
+    }
+}
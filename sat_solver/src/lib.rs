@@ -0,0 +1,3346 @@
+//! A CDCL SAT solver with two-watched-literal unit propagation.
+
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+mod builder;
+mod dimacs;
+mod dot;
+mod encode;
+mod gzip;
+mod heap;
+mod ipasir;
+mod lit;
+mod maxsat;
+mod mus;
+mod portfolio;
+mod preprocess;
+mod xor;
+#[cfg(test)]
+mod testing;
+
+pub use builder::CnfBuilder;
+pub use dimacs::{parse_assumptions, parse_dimacs, write_dimacs, Dimacs, ParseError};
+pub use encode::{encode_at_most_k, encode_at_most_one};
+pub use gzip::{gunzip, is_gzip};
+pub use ipasir::IncrementalSolver;
+pub use lit::{Lit, LitInt, Var};
+pub use portfolio::solve_portfolio;
+
+use heap::VarHeap;
+use xor::XorSystem;
+
+pub type Clause = Vec<Lit>;
+
+/// Callback for `Solver::on_learn`.
+pub type LearnCallback = Box<dyn FnMut(&[Lit])>;
+
+/// Callback for `Solver::on_progress`.
+pub type ProgressCallback = Box<dyn FnMut(&Progress)>;
+
+/// Where a running search stands, as passed to `Solver::on_progress`.
+#[derive(Debug)]
+pub struct Progress<'a> {
+    pub stats: &'a Stats,
+    /// Learned clauses currently in the clause database.
+    pub learned_clauses: usize,
+    /// Literal decided on the current decision level, None at the root.
+    pub decision: Option<Lit>,
+}
+
+// Variable values packed as two bit-vectors, one bit per variable in each:
+// whether the variable is assigned, and its value if so. Keeps the hot
+// val() path on a handful of cache lines instead of a byte-per-variable Vec.
+struct Assignment {
+    assigned: Vec<u64>,
+    values: Vec<u64>,
+}
+
+impl Assignment {
+    fn new(len: usize) -> Self {
+        let words = len.div_ceil(64);
+        Assignment {
+            assigned: vec![0; words],
+            values: vec![0; words],
+        }
+    }
+
+    #[inline(always)]
+    fn get(&self, var: usize) -> Option<bool> {
+        let (word, bit) = (var / 64, 1u64 << (var % 64));
+        if self.assigned[word] & bit == 0 {
+            None
+        } else {
+            Some(self.values[word] & bit != 0)
+        }
+    }
+
+    #[inline(always)]
+    fn set(&mut self, var: usize, val: bool) {
+        let (word, bit) = (var / 64, 1u64 << (var % 64));
+        self.assigned[word] |= bit;
+        if val {
+            self.values[word] |= bit;
+        } else {
+            self.values[word] &= !bit;
+        }
+    }
+
+    #[inline(always)]
+    fn clear(&mut self, var: usize) {
+        let (word, bit) = (var / 64, 1u64 << (var % 64));
+        self.assigned[word] &= !bit;
+    }
+
+    // Makes room for `len` variables, the new ones unassigned.
+    fn grow(&mut self, len: usize) {
+        let words = len.div_ceil(64);
+        if self.assigned.len() < words {
+            self.assigned.resize(words, 0);
+            self.values.resize(words, 0);
+        }
+    }
+}
+
+/// Outcome of a bounded solve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolveResult {
+    Sat,
+    Unsat,
+    /// The search gave up before reaching a verdict.
+    Unknown,
+}
+
+// VSIDS: the bump increment grows by 1/decay per conflict, which decays all
+// earlier bumps relative to new ones without touching every activity.
+const VAR_DECAY: f64 = 0.95;
+const ACTIVITY_LIMIT: f64 = 1e100;
+
+// Conflicts between restarts are the base times the next Luby number.
+const RESTART_BASE: u64 = 100;
+
+// LBD restarts compare the average LBD of the last LBD_WINDOW learned
+// clauses with the long-run average, restarting once the recent one times
+// LBD_MARGIN exceeds it (Glucose's K = 0.8).
+const LBD_WINDOW: usize = 50;
+const LBD_MARGIN: f64 = 0.8;
+
+// Agility decays by AGILITY_DECAY per assignment and gains 1 - AGILITY_DECAY
+// whenever one flips a saved phase. Below AGILITY_LIMIT the search counts as
+// focused, and the agility brake skips its restarts.
+const AGILITY_DECAY: f64 = 0.9999;
+const AGILITY_LIMIT: f64 = 0.25;
+
+// Conflicts before the first learned clause database reduction; every later
+// one waits REDUCE_INC conflicts longer than the one before.
+const REDUCE_INTERVAL: u64 = 2000;
+const REDUCE_INC: u64 = 300;
+
+// Lookahead tries at most this many variables per decision, the ones with
+// the most occurrences, and scores each by LOOKAHEAD_WEIGHT times the
+// product of the assignments its two values imply plus their sum, so a
+// variable that does well both ways wins over one that does well only one way.
+const LOOKAHEAD_CANDIDATES: usize = 64;
+const LOOKAHEAD_WEIGHT: usize = 1024;
+
+// A time limit is checked once every this many conflicts, keeping clock reads
+// off the conflict path.
+const DEADLINE_CHECK: u64 = 64;
+
+// A decision level hashed to one of 64 bits, so a set of levels fits in a
+// word and two sets can be tested for overlap with a single and.
+fn abstract_level(level: usize) -> u64 {
+    1 << (level % 64)
+}
+
+// Element `i` (0-based) of the Luby sequence 1, 1, 2, 1, 1, 2, 4, 1, ...
+fn luby(mut i: u64) -> u64 {
+    let mut size = 1;
+    let mut seq = 0;
+    while size < i + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) >> 1;
+        seq -= 1;
+        i %= size;
+    }
+    1 << seq
+}
+
+// xorshift64* generator, used to break branching ties reproducibly.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // uniform in 0..n, n > 0
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/// Branching heuristic used to pick decision variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    /// Activity-based VSIDS backed by a heap (the default).
+    Vsids,
+    /// The original scan for the variable in most unresolved clauses.
+    Occurrences,
+    /// Tries both values of the most frequent unassigned variables and
+    /// branches on the one whose values imply the most assignments. Far
+    /// slower per decision, but it can need far fewer decisions on small
+    /// hard formulas.
+    Lookahead,
+}
+
+/// When the search restarts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restarts {
+    /// After a number of conflicts following the Luby sequence.
+    Luby,
+    /// Glucose-style: whenever recently learned clauses have a clearly
+    /// higher LBD than the long-run average, a sign the search is stuck in a
+    /// bad region (the default).
+    Lbd,
+}
+
+/// Search counters, accumulated over every solve of a solver.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    /// Branching decisions, not counting assumptions or pure literals.
+    pub decisions: u64,
+    /// Assignments whose consequences were propagated.
+    pub propagations: u64,
+    pub conflicts: u64,
+    pub restarts: u64,
+    /// Clauses added by conflict analysis.
+    pub learned: u64,
+    /// Learned clauses dropped again by clause database reduction.
+    pub deleted: u64,
+    /// Sum of the LBDs of the learned clauses, see `average_lbd()`.
+    pub lbd_total: u64,
+    /// Current agility, unlike the counters: a moving average of how often
+    /// an assignment flips the variable's saved phase, from 0 for a search
+    /// that keeps to its phases to 1 for one that flips every time.
+    pub agility: f64,
+}
+
+impl Stats {
+    /// Average LBD (number of decision levels) of the learned clauses, 0
+    /// before any were learned.
+    pub fn average_lbd(&self) -> f64 {
+        if self.learned == 0 {
+            return 0.0;
+        }
+        self.lbd_total as f64 / self.learned as f64
+    }
+}
+
+/// Options fixed when a solver is built, see `Solver::with_config`. The
+/// default is the solver's standard behaviour.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolverConfig {
+    /// VSIDS decay factor in (0, 1]: how much older conflicts count next to
+    /// new ones. 1 never decays.
+    pub var_decay: f64,
+    /// How decision variables are picked, see also `Solver::set_heuristic`.
+    pub heuristic: Heuristic,
+    /// When to restart the search.
+    pub restarts: Restarts,
+    /// Conflicts before the first Luby restart, scaled by the Luby sequence
+    /// for later ones. 0 disables restarts under either policy.
+    pub restart_base: u64,
+    /// Skip restarts that fall due while the agility (see `Stats::agility`)
+    /// is low, so a search that has settled on its phases isn't thrown off
+    /// them. The restart schedule moves on as if they had happened.
+    pub agility_brake: bool,
+    /// Assign pure literals whenever the search is back at the root.
+    pub pure_literals: bool,
+    /// Seed for breaking ties between equally good branching variables; the
+    /// same seed gives the same decision trace. 0 leaves ties to the lowest
+    /// variable.
+    pub seed: u64,
+    /// Run bounded variable elimination during preprocessing. Models are
+    /// still reported over every variable, and model counting and enumeration
+    /// still cover the formula as given, but `val()` says nothing about
+    /// eliminated variables, and using one in a later assumption, added or
+    /// temporary clause or XOR constraint panics.
+    pub eliminate: bool,
+    /// Merge equivalent literals during preprocessing: where binary clauses
+    /// make literals imply each other in a cycle, all but one are replaced
+    /// by that one and their variables are eliminated, with the same
+    /// restrictions as under `eliminate`.
+    pub equivalences: bool,
+    /// Remove blocked clauses during preprocessing: clauses with a literal
+    /// whose resolvents with every clause containing its negation are all
+    /// tautologies. The formula stays satisfiable exactly when it was, and
+    /// models are fixed up to satisfy the removed clauses too, but not all
+    /// of its models are kept: the variables the clauses were blocked on
+    /// must not appear in later assumptions, added or temporary clauses or
+    /// XOR constraints, and model counting and enumeration count the models
+    /// of the smaller formula.
+    pub blocked_clauses: bool,
+    /// Conflicts before the learned clauses are first thinned out, deleting
+    /// the worse half by LBD; the gap grows after every reduction. 0 keeps
+    /// every learned clause.
+    pub reduce_interval: u64,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            var_decay: VAR_DECAY,
+            heuristic: Heuristic::Vsids,
+            restarts: Restarts::Lbd,
+            restart_base: RESTART_BASE,
+            agility_brake: false,
+            pure_literals: true,
+            seed: 0,
+            eliminate: false,
+            equivalences: false,
+            blocked_clauses: false,
+            reduce_interval: REDUCE_INTERVAL,
+        }
+    }
+}
+
+/// Returns true if every clause has a literal that is true under `model`,
+/// which is indexed by variable like the vector `Solver::model` returns.
+pub fn check_model(clauses: &[Clause], model: &[bool]) -> bool {
+    clauses.iter().all(|clause| {
+        clause.iter().any(|&lit| {
+            model
+                .get(lit.var() as usize)
+                .is_some_and(|&value| value == lit.is_positive())
+        })
+    })
+}
+
+// Sorts `clause` so the literals of each variable are adjacent and drops
+// repeated literals, which would otherwise be watched twice or counted twice
+// in the occurrence counts. Returns false for a tautology (some x and -x),
+// which constrains nothing and can be dropped.
+fn normalize(clause: &mut Clause) -> bool {
+    clause.sort_unstable_by_key(|lit| lit.index());
+    clause.dedup();
+    !clause.windows(2).any(|pair| pair[0].var() == pair[1].var())
+}
+
+// Positions of the two literals a new clause watches: its first two, or its
+// only literal twice for a unit clause so neither points past the clause
+// (propagate() never moves a unit clause's watches).
+fn first_watches(clause: &[Lit]) -> (usize, usize) {
+    (0, usize::from(clause.len() > 1))
+}
+
+// What a block of scratch clauses overwrites, restored by end_scratch().
+struct Scratch {
+    root_len: usize,
+    last_result: SolveResult,
+    model: Vec<bool>,
+    proof: Option<Box<dyn Write>>,
+    num_clauses: usize,
+    num_original: usize,
+    protected_len: usize,
+}
+
+// Iterator behind Solver::solutions(). It enumerates on `copy` instead of
+// `solver` when preprocessing left `solver` without some of the models.
+struct Solutions<'a> {
+    solver: &'a mut Solver,
+    copy: Option<Box<Solver>>,
+    scratch: Option<Scratch>,
+    done: bool,
+}
+
+impl Iterator for Solutions<'_> {
+    type Item = Vec<bool>;
+
+    fn next(&mut self) -> Option<Vec<bool>> {
+        let solver = match &mut self.copy {
+            Some(copy) => copy,
+            None => &mut *self.solver,
+        };
+        if self.done || !solver.solve() {
+            self.done = true;
+            return None;
+        }
+        let model = mem::take(&mut solver.model);
+        let blocking = (1..=solver.num_vars)
+            .map(|var| Lit::new(var as Var, !model[var]))
+            .collect();
+        solver.add_scratch_clause(blocking);
+        Some(model)
+    }
+}
+
+impl Drop for Solutions<'_> {
+    fn drop(&mut self) {
+        if let Some(scratch) = self.scratch.take() {
+            self.solver.end_scratch(scratch);
+        }
+    }
+}
+
+pub struct Solver {
+    clauses: Vec<Clause>,
+    // the formula as given to new() and add_clause(), before preprocessing
+    original_clauses: Vec<Clause>,
+    num_vars: usize,
+    assignment: Assignment,
+    history: Vec<Lit>,
+    positives: Vec<usize>,
+    negatives: Vec<usize>,
+    watches: Vec<(usize, usize)>,
+    // literal block distance of each learned clause: the number of decision
+    // levels among its literals when it was learned; 0 for all other clauses
+    lbd: Vec<u32>,
+    watch_lists: Vec<Vec<usize>>,
+    // binary clauses skip the watch lists: for each binary clause (x y) the
+    // list of x holds (y, clause index), which is implied as soon as x is false
+    binary_lists: Vec<Vec<(Lit, usize)>>,
+    // per variable: the decision level it was assigned at and the clause that
+    // implied it (None for decisions and root-level seeds)
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    // history length at the start of each decision level
+    trail_lim: Vec<usize>,
+    // history[qhead..] are assignments bcp() hasn't propagated yet
+    qhead: usize,
+    // literals decided first, one per level, by solve_under_assumptions()
+    assumptions: Vec<Lit>,
+    // assumptions responsible for the last assumption-level Unsat
+    core: Vec<Lit>,
+    // scratch marks for conflict analysis
+    seen: Vec<bool>,
+    // per-variable scratch counts for pick_variable(), kept between calls so
+    // each decision reuses the allocation
+    scores: Vec<usize>,
+    activity: Vec<f64>,
+    var_inc: f64,
+    order: VarHeap,
+    // last value each variable was assigned, reused as its decision polarity
+    phases: Vec<Option<bool>>,
+    model: Vec<bool>,
+    // verdict of the most recent solve(), Unknown before the first call
+    last_result: SolveResult,
+    stats: Stats,
+    conflict_budget: u64,
+    // when the running solve_with_timeout() gives up
+    deadline: Option<Instant>,
+    // set from outside (e.g. a signal handler) to stop the search early
+    interrupt: Option<Arc<AtomicBool>>,
+    // conflict count at which reduce_db() runs next, and the gap after that
+    reduce_at: u64,
+    reduce_gap: u64,
+    // LBDs of the most recently learned clauses for LBD restarts, and their sum
+    recent_lbds: VecDeque<u32>,
+    recent_lbd_sum: u64,
+    // clauses below this index are never deleted or moved by reduce_db(), so
+    // the clause counts scratch blocks truncate back to stay valid
+    protected_len: usize,
+    // DRAT proof sink and the first error writing to it
+    proof: Option<Box<dyn Write>>,
+    proof_error: Option<io::Error>,
+    // preprocessing steps (deletion flag, clause) taken before a proof sink
+    // was set, written out once one is; dropped at the first solve without one
+    pending_proof: Vec<(bool, Clause)>,
+    // called with every clause conflict analysis derives
+    on_learn: Option<LearnCallback>,
+    // called with the search's progress at most once per interval, and when
+    // it last was
+    on_progress: Option<(Duration, ProgressCallback)>,
+    last_progress: Instant,
+    // where set_conflict_dump() wants the implication graph of the next
+    // conflict, and how writing it went once it has been
+    conflict_dump: Option<Box<dyn Write>>,
+    conflict_dump_result: Option<io::Result<()>>,
+    config: SolverConfig,
+    // clauses removed by variable elimination, each with the literal of its
+    // eliminated variable, in elimination order; replayed backwards to give
+    // those variables values in the model
+    elim_stack: Vec<(Lit, Clause)>,
+    eliminated: Vec<bool>,
+    // tie-breaking randomness, None for seed 0
+    rng: Option<Rng>,
+    xors: XorSystem,
+    // variables a projected model counter should count over, see
+    // set_independent_support()
+    independent_support: Vec<Var>,
+    // whether preprocess() has run, which it only may once
+    preprocessed: bool,
+}
+
+impl Solver {
+    /// Builds a solver for `clauses` over variables 1 to `num_vars`, or to the
+    /// largest variable the clauses mention if that is larger.
+    pub fn new(clauses: Vec<Clause>, num_vars: usize) -> Self {
+        Solver::with_config(clauses, num_vars, SolverConfig::default())
+    }
+
+    /// Like `new()`, taking the clauses from an iterator, e.g. a generator
+    /// that produces them one at a time. This saves the caller collecting
+    /// them, not memory: they are still gathered into a `Vec` for `new()`.
+    pub fn from_clauses(clauses: impl IntoIterator<Item = Clause>, num_vars: usize) -> Self {
+        Solver::new(clauses.into_iter().collect(), num_vars)
+    }
+
+    /// Like `new()`, with non-default options.
+    pub fn with_config(clauses: Vec<Clause>, num_vars: usize, config: SolverConfig) -> Self {
+        let mut solver = Solver::unprocessed(clauses, num_vars, config);
+        solver.preprocess();
+        solver
+    }
+
+    /// Like `new()`, but without preprocessing, so the search runs on the
+    /// clauses as given, in their order; only repeated literals and
+    /// tautologies are dropped, since the watch lists can't hold them.
+    /// `preprocess()` can still be called before solving.
+    pub fn new_raw(clauses: Vec<Clause>, num_vars: usize) -> Self {
+        let mut solver = Solver::unprocessed(clauses, num_vars, SolverConfig::default());
+        solver.init_search();
+        solver
+    }
+
+    // A solver over `clauses` with nothing set up for searching them yet.
+    fn unprocessed(mut clauses: Vec<Clause>, num_vars: usize, config: SolverConfig) -> Self {
+        // an understated count would leave the per-variable vectors too short
+        let max_var = clauses.iter().flatten().map(|lit| lit.var() as usize).max();
+        let num_vars = max_var.map_or(num_vars, |max_var| max_var.max(num_vars));
+        let original_clauses = clauses.clone();
+        clauses.retain_mut(normalize);
+        Solver {
+            clauses,
+            original_clauses,
+            num_vars,
+            assignment: Assignment::new(num_vars + 1),
+            history: Vec::with_capacity(num_vars + 1),
+            positives: vec![0; num_vars + 1],
+            negatives: vec![0; num_vars + 1],
+            watches: Vec::new(),
+            lbd: Vec::new(),
+            watch_lists: vec![Vec::new(); 2 * (num_vars + 1)],
+            binary_lists: vec![Vec::new(); 2 * (num_vars + 1)],
+            levels: vec![0; num_vars + 1],
+            reasons: vec![None; num_vars + 1],
+            trail_lim: Vec::new(),
+            qhead: 0,
+            assumptions: Vec::new(),
+            core: Vec::new(),
+            seen: vec![false; num_vars + 1],
+            scores: vec![0; num_vars + 1],
+            activity: vec![0.0; num_vars + 1],
+            var_inc: 1.0,
+            order: VarHeap::new(num_vars),
+            phases: vec![None; num_vars + 1],
+            model: Vec::new(),
+            last_result: SolveResult::Unknown,
+            // starting out agile keeps the brake off until the search settles
+            stats: Stats {
+                agility: 1.0,
+                ..Stats::default()
+            },
+            conflict_budget: u64::MAX,
+            deadline: None,
+            interrupt: None,
+            reduce_at: config.reduce_interval,
+            reduce_gap: config.reduce_interval,
+            recent_lbds: VecDeque::with_capacity(LBD_WINDOW),
+            recent_lbd_sum: 0,
+            protected_len: 0,
+            proof: None,
+            proof_error: None,
+            pending_proof: Vec::new(),
+            on_learn: None,
+            on_progress: None,
+            last_progress: Instant::now(),
+            conflict_dump: None,
+            conflict_dump_result: None,
+            elim_stack: Vec::new(),
+            eliminated: vec![false; num_vars + 1],
+            rng: (config.seed != 0).then(|| Rng::new(config.seed)),
+            xors: XorSystem::default(),
+            independent_support: Vec::new(),
+            preprocessed: false,
+            config,
+        }
+    }
+
+    /// Simplifies the clauses as configured: subsumption and self-subsuming
+    /// resolution, plus equivalent literal substitution and variable
+    /// elimination if enabled. `new()` does this already, so it is only
+    /// useful after `new_raw()`; any later call does nothing. Learned clauses
+    /// are dropped and root-level assignments undone, and the branching
+    /// scores start over. Not to be called inside `with_temp_clause`.
+    pub fn preprocess(&mut self) {
+        if self.preprocessed {
+            return;
+        }
+        self.preprocessed = true;
+        self.backtrack_to_level(0);
+        self.backtrack(0);
+        if !self.lbd.is_empty() {
+            let lbd = mem::take(&mut self.lbd);
+            let mut idx = 0;
+            self.clauses.retain(|_| {
+                idx += 1;
+                lbd[idx - 1] == 0
+            });
+        }
+
+        if self.config.equivalences {
+            self.substitute_equivalences();
+        }
+        self.subsume();
+        if self.config.eliminate {
+            self.eliminate();
+        }
+        if self.config.blocked_clauses {
+            self.eliminate_blocked();
+        }
+
+        // So we are checking shortest clauses first, exploring variables that affect the first clauses. Optimization
+        self.clauses.sort_by_key(|c| c.len());
+
+        self.init_search();
+    }
+
+    // Sets up watches, occurrence counts and branching scores for the current
+    // clauses, all of which count as original.
+    fn init_search(&mut self) {
+        self.lbd = vec![0; self.clauses.len()];
+        self.init_watches();
+        #[cfg(debug_assertions)]
+        self.check_watch_invariants();
+
+        // Occurrence counts, used for branching polarity and initial activity
+        self.positives.fill(0);
+        self.negatives.fill(0);
+        for clause in &self.clauses {
+            for &lit in clause {
+                let var = lit.var() as usize;
+                if lit.is_positive() {
+                    self.positives[var] += 1;
+                } else {
+                    self.negatives[var] += 1;
+                }
+            }
+        }
+
+        self.order = VarHeap::new(self.num_vars);
+        self.init_activity();
+    }
+
+    // Watches the first two literals of every clause.
+    fn init_watches(&mut self) {
+        for list in &mut self.watch_lists {
+            list.clear();
+        }
+        for list in &mut self.binary_lists {
+            list.clear();
+        }
+        self.watches = self.clauses.iter().map(|clause| first_watches(clause)).collect();
+        for idx in 0..self.clauses.len() {
+            self.watch_clause(idx);
+        }
+    }
+
+    // Panics unless every clause is watched where `watches` says: at two
+    // distinct, in-bounds positions, and by exactly the watch lists of those
+    // two literals. Binary clauses are in the binary lists of both their
+    // literals instead, and a unit clause only in the list of its literal.
+    #[cfg(debug_assertions)]
+    fn check_watch_invariants(&self) {
+        let mut watched_by = vec![Vec::new(); self.clauses.len()];
+        for (lit_idx, list) in self.watch_lists.iter().enumerate() {
+            for &idx in list {
+                watched_by[idx].push(lit_idx);
+            }
+        }
+        let mut binary_in = vec![Vec::new(); self.clauses.len()];
+        for (lit_idx, list) in self.binary_lists.iter().enumerate() {
+            for &(other, idx) in list {
+                binary_in[idx].push((lit_idx, other));
+            }
+        }
+
+        for (idx, clause) in self.clauses.iter().enumerate() {
+            let (first, second) = self.watches[idx];
+            if !clause.is_empty() {
+                let in_bounds = first < clause.len() && second < clause.len();
+                assert!(in_bounds, "clause {} watches out of bounds", idx);
+            }
+            let (mut watches, mut binary) = (Vec::new(), Vec::new());
+            match clause.len() {
+                0 => {}
+                1 => watches.push(clause[0].index()),
+                2 => binary = vec![(clause[0].index(), clause[1]), (clause[1].index(), clause[0])],
+                _ => {
+                    assert_ne!(first, second, "clause {} watches one literal twice", idx);
+                    watches = vec![clause[first].index(), clause[second].index()];
+                }
+            }
+            watches.sort_unstable();
+            watched_by[idx].sort_unstable();
+            assert_eq!(watched_by[idx], watches, "clause {} is in the wrong watch lists", idx);
+            binary.sort_unstable();
+            binary_in[idx].sort_unstable();
+            assert_eq!(binary_in[idx], binary, "clause {} is in the wrong binary lists", idx);
+        }
+    }
+
+    // VSIDS starts out ordered by occurrence count; the seeded jitter stays
+    // below 1 so it only reorders variables with equal counts.
+    fn init_activity(&mut self) {
+        for v in 1..=self.num_vars {
+            let jitter = self.rng.as_mut().map_or(0.0, |rng| rng.next_f64() / 2.0);
+            self.activity[v] = (self.positives[v] + self.negatives[v]) as f64 + jitter;
+            if !self.eliminated[v] {
+                self.order.insert(v, &self.activity);
+            }
+        }
+    }
+
+    /// Puts the solver back in the state `new()` left it in, plus any clauses
+    /// added and learned since: root-level assignments are undone, and
+    /// branching scores, saved phases and the tie-breaking seed start over,
+    /// so the next solve runs like a fresh solver's that starts with the
+    /// learned clauses. XOR constraints are kept and the stats keep counting.
+    /// `solve()` gives right answers without this; it only makes a run
+    /// independent of the heuristics of the ones before. Not to be called
+    /// inside `with_temp_clause`.
+    pub fn reset(&mut self) {
+        self.backtrack_to_level(0);
+        self.backtrack(0);
+        // Assumptions and pure literals only ever enter the search as
+        // decisions, so every learned clause follows from the clauses and XOR
+        // constraints alone and stays valid; what was learned from temporary
+        // clauses is gone with them.
+        self.init_watches();
+        self.last_result = SolveResult::Unknown;
+        self.model.clear();
+        self.core.clear();
+        self.phases.fill(None);
+        self.var_inc = 1.0;
+        self.reduce_at = self.stats.conflicts + self.config.reduce_interval;
+        self.reduce_gap = self.config.reduce_interval;
+        self.forget_recent_lbds();
+        self.rng = (self.config.seed != 0).then(|| Rng::new(self.config.seed));
+        self.order = VarHeap::new(self.num_vars);
+        self.init_activity();
+    }
+    
+    fn watch_clause(&mut self, idx: usize) {
+        let clause = &self.clauses[idx];
+        if clause.is_empty() {
+            return;
+        }
+        debug_assert!(clause.len() < 2 || clause[0] != clause[1], "literal watched twice");
+        if let &[a, b] = clause.as_slice() {
+            self.binary_lists[a.index()].push((b, idx));
+            self.binary_lists[b.index()].push((a, idx));
+            return;
+        }
+
+        self.watch_lists[clause[0].index()].push(idx);
+
+        if clause.len() > 1 {
+            self.watch_lists[clause[1].index()].push(idx);
+        }
+    }
+
+    /// Adds `lits` as a temporary clause, runs `f` (typically a `solve()`) and then
+    /// retracts the clause together with every clause learned while it was present.
+    /// Root-level assignments derived meanwhile are undone and the last verdict and
+    /// model are restored; the search trail is left at the root level. Variables
+    /// above `num_vars` are added to the formula and stay.
+    pub fn with_temp_clause<T>(&mut self, lits: &[Lit], f: impl FnOnce(&mut Solver) -> T) -> T {
+        let mut lits = lits.to_vec();
+        let keep = normalize(&mut lits);
+        self.with_scratch_clauses(|solver| {
+            if keep {
+                solver.add_scratch_clause(lits);
+            }
+            f(solver)
+        })
+    }
+
+    // Runs `f` and then retracts every clause added while it ran, as
+    // described for `with_temp_clause`.
+    fn with_scratch_clauses<T>(&mut self, f: impl FnOnce(&mut Solver) -> T) -> T {
+        let scratch = self.begin_scratch();
+        let result = f(self);
+        self.end_scratch(scratch);
+        result
+    }
+
+    fn begin_scratch(&mut self) -> Scratch {
+        Scratch {
+            root_len: self.trail_lim.first().copied().unwrap_or(self.history.len()),
+            last_result: self.last_result,
+            model: mem::take(&mut self.model),
+            // what is learned from scratch clauses doesn't follow from the formula
+            proof: self.proof.take(),
+            num_clauses: self.clauses.len(),
+            num_original: self.original_clauses.len(),
+            protected_len: mem::replace(&mut self.protected_len, self.clauses.len()),
+        }
+    }
+
+    fn end_scratch(&mut self, scratch: Scratch) {
+        self.backtrack_to_level(0);
+        self.backtrack(scratch.root_len);
+        self.last_result = scratch.last_result;
+        self.model = scratch.model;
+        self.proof = scratch.proof;
+        self.protected_len = scratch.protected_len;
+        self.truncate_clauses(scratch.num_clauses);
+        self.original_clauses.truncate(scratch.num_original);
+    }
+
+    // Drops every clause from index `len` on, along with its watches.
+    fn truncate_clauses(&mut self, len: usize) {
+        for list in &mut self.watch_lists {
+            list.retain(|&c| c < len);
+        }
+        for list in &mut self.binary_lists {
+            list.retain(|&(_, c)| c < len);
+        }
+        self.watches.truncate(len);
+        self.lbd.truncate(len);
+        self.clauses.truncate(len);
+    }
+
+    // Extends the solver to variables up to `num_vars`, if it has fewer. The
+    // new variables are unassigned and occur in no clause.
+    fn grow_vars(&mut self, num_vars: usize) {
+        if num_vars <= self.num_vars {
+            return;
+        }
+        self.assignment.grow(num_vars + 1);
+        self.positives.resize(num_vars + 1, 0);
+        self.negatives.resize(num_vars + 1, 0);
+        self.watch_lists.resize(2 * (num_vars + 1), Vec::new());
+        self.binary_lists.resize(2 * (num_vars + 1), Vec::new());
+        self.levels.resize(num_vars + 1, 0);
+        self.reasons.resize(num_vars + 1, None);
+        self.seen.resize(num_vars + 1, false);
+        self.scores.resize(num_vars + 1, 0);
+        self.activity.resize(num_vars + 1, 0.0);
+        self.phases.resize(num_vars + 1, None);
+        self.eliminated.resize(num_vars + 1, false);
+        self.order.grow(num_vars);
+        for v in self.num_vars + 1..=num_vars {
+            self.order.insert(v, &self.activity);
+        }
+        self.num_vars = num_vars;
+    }
+
+    // Makes the variables of `lits` ready for the search: new ones are added,
+    // and eliminated ones are refused, since the clauses constraining them
+    // are gone.
+    fn use_vars(&mut self, lits: &[Lit]) {
+        for lit in lits {
+            let var = lit.var() as usize;
+            if var <= self.num_vars && self.eliminated[var] {
+                panic!("variable {var} was removed by variable elimination");
+            }
+        }
+        if let Some(max_var) = lits.iter().map(|lit| lit.var() as usize).max() {
+            self.grow_vars(max_var);
+        }
+    }
+
+    // Adds a clause that isn't part of the formula; only valid inside
+    // with_scratch_clauses(). New variables stay once it is retracted.
+    fn add_scratch_clause(&mut self, lits: Clause) {
+        self.use_vars(&lits);
+        self.push_clause(lits, 0);
+    }
+
+    /// Adds `clause` to the formula for all later solves. Like the clauses
+    /// given to `new()` it counts towards the branching scores, but it skips
+    /// preprocessing. Added inside `with_temp_clause`, it is retracted with
+    /// the temporary clause. Variables above `num_vars` are added to the
+    /// formula.
+    pub fn add_clause(&mut self, mut clause: Clause) {
+        self.original_clauses.push(clause.clone());
+        if !normalize(&mut clause) {
+            return;
+        }
+        self.use_vars(&clause);
+        self.backtrack_to_level(0);
+        for &lit in &clause {
+            let var = lit.var() as usize;
+            if lit.is_positive() {
+                self.positives[var] += 1;
+            } else {
+                self.negatives[var] += 1;
+            }
+        }
+        self.push_clause(clause, 0);
+    }
+
+    // Appends a clause and watches it; `lbd` is 0 unless it was learned.
+    // Root-level facts that already falsify its watches are dealt with when
+    // the next search re-propagates the root. Returns the clause's index.
+    fn push_clause(&mut self, clause: Clause, lbd: u32) -> usize {
+        let idx = self.clauses.len();
+        self.watches.push(first_watches(&clause));
+        self.lbd.push(lbd);
+        self.clauses.push(clause);
+        self.watch_clause(idx);
+        idx
+    }
+
+    /// Counts the satisfying assignments over all `num_vars` variables
+    /// (saturating at `u128::MAX`). Each model found is shrunk to a partial
+    /// assignment that still satisfies every clause, which accounts for
+    /// 2^k models when k variables are left free, and is then blocked by
+    /// adding its negation before solving again. The blocking clauses are
+    /// retracted afterwards, as with `with_temp_clause`. This enumerates
+    /// solutions, so it only suits small or tightly constrained formulas.
+    /// If preprocessing removed variables or clauses, which can merge or
+    /// add models, the formula as given is counted by a fresh solver.
+    pub fn count_models(&mut self) -> u128 {
+        if let Some(mut solver) = self.unpreprocessed_copy() {
+            return solver.count_models();
+        }
+        self.with_scratch_clauses(|solver| {
+            let mut count: u128 = 0;
+            while solver.solve() {
+                let cube = solver.shrink_model();
+                let free = (solver.num_vars - cube.len()) as u32;
+                count = count.saturating_add(1u128.checked_shl(free).unwrap_or(u128::MAX));
+                solver.add_scratch_clause(cube.iter().map(|&lit| -lit).collect());
+            }
+            count
+        })
+    }
+
+    /// Lazily yields every satisfying assignment, indexed like `model()`.
+    /// Each one is blocked by adding its negation before searching for the
+    /// next, and the iterator ends once the formula has become UNSAT. The
+    /// blocking clauses are retracted when the iterator is dropped. As with
+    /// `count_models`, a formula preprocessing removed variables or clauses
+    /// from is enumerated by a fresh solver as given.
+    pub fn solutions(&mut self) -> impl Iterator<Item = Vec<bool>> + '_ {
+        let copy = self.unpreprocessed_copy().map(Box::new);
+        let scratch = copy.is_none().then(|| self.begin_scratch());
+        Solutions {
+            solver: self,
+            copy,
+            scratch,
+            done: false,
+        }
+    }
+
+    /// Returns the backbone: the literals that are true in every model,
+    /// empty if there is none. Each literal of a first model is tested by
+    /// solving with its negation assumed, which fails exactly for backbone
+    /// literals, and every model found along the way rules out the
+    /// literals it falsifies, so it takes up to one solve per variable.
+    /// Literals whose test was stopped by an interrupt are left out. The
+    /// last verdict and model are restored afterwards. As with
+    /// `count_models`, a formula preprocessing removed variables or clauses
+    /// from is solved afresh as given.
+    pub fn backbone(&mut self) -> Vec<Lit> {
+        if let Some(mut solver) = self.unpreprocessed_copy() {
+            return solver.backbone();
+        }
+        let last_result = self.last_result;
+        let model = mem::take(&mut self.model);
+
+        let mut backbone = Vec::new();
+        if self.solve() {
+            let mut candidates: Vec<Option<Lit>> = (0..=self.num_vars)
+                .map(|v| (v > 0 && !self.eliminated[v]).then(|| Lit::new(v as Var, self.model[v])))
+                .collect();
+            for v in 1..=self.num_vars {
+                let Some(lit) = candidates[v] else {
+                    continue;
+                };
+                match self.solve_under_assumptions(&[-lit]) {
+                    SolveResult::Unsat => backbone.push(lit),
+                    SolveResult::Sat => {
+                        for (w, candidate) in candidates.iter_mut().enumerate().skip(v) {
+                            if candidate.is_some_and(|lit| self.model[w] != lit.is_positive()) {
+                                *candidate = None;
+                            }
+                        }
+                    }
+                    SolveResult::Unknown => {}
+                }
+            }
+        }
+
+        self.last_result = last_result;
+        self.model = model;
+        backbone
+    }
+
+    // A solver over a formula equivalent to the one given, for the queries
+    // that need all of its models, if variable elimination removed clauses
+    // from this one; None if it didn't. The removed clauses together with
+    // what is left imply the resolvents, so putting them back is enough. It
+    // shares the XOR constraints and interrupt.
+    fn unpreprocessed_copy(&self) -> Option<Solver> {
+        if self.elim_stack.is_empty() {
+            return None;
+        }
+        let mut clauses = self.clauses.clone();
+        clauses.extend(self.elim_stack.iter().map(|(_, clause)| clause.clone()));
+        let mut solver = Solver::new(clauses, self.num_vars);
+        solver.xors = self.xors.clone();
+        solver.interrupt = self.interrupt.clone();
+        Some(solver)
+    }
+
+    // Drops literals from the current model, one variable at a time, as long
+    // as every clause keeps a true literal and no XOR constraint is involved,
+    // and returns the remaining literals.
+    fn shrink_model(&self) -> Vec<Lit> {
+        let mut true_counts = vec![0usize; self.clauses.len()];
+        let mut occurrences = vec![Vec::new(); self.num_vars + 1];
+        for (idx, clause) in self.clauses.iter().enumerate() {
+            for &lit in clause {
+                let var = lit.var() as usize;
+                // a repeated literal mustn't count twice
+                if self.model[var] == lit.is_positive() && occurrences[var].last() != Some(&idx) {
+                    true_counts[idx] += 1;
+                    occurrences[var].push(idx);
+                }
+            }
+        }
+
+        let mut cube = Vec::new();
+        for (var, occurs) in occurrences.iter().enumerate().skip(1) {
+            // flipping a variable of an XOR constraint always breaks it
+            let needed =
+                occurs.iter().any(|&idx| true_counts[idx] == 1) || self.xors.contains(var);
+            if needed {
+                cube.push(Lit::new(var as Var, self.model[var]));
+            } else {
+                for &idx in occurs {
+                    true_counts[idx] -= 1;
+                }
+            }
+        }
+        cube
+    }
+
+    /// Releases spare capacity left behind by earlier solves so a long-lived
+    /// solver doesn't keep its peak footprint between query batches.
+    pub fn compact(&mut self) {
+        self.history.shrink_to_fit();
+        self.clauses.shrink_to_fit();
+        self.original_clauses.shrink_to_fit();
+        self.watches.shrink_to_fit();
+        self.lbd.shrink_to_fit();
+        for list in &mut self.watch_lists {
+            list.shrink_to_fit();
+        }
+        for list in &mut self.binary_lists {
+            list.shrink_to_fit();
+        }
+    }
+
+    /// Propagates the root level in full, then removes every clause it
+    /// satisfies and strips the literals it falsifies from the rest, e.g.
+    /// after unit clauses were added between solves. The root-level facts
+    /// themselves are kept as unit clauses, so the formula keeps its models
+    /// and later solves, `reset()` included, give the same answers. Does
+    /// nothing if the root level is contradictory. Not to be called inside
+    /// `with_temp_clause`.
+    pub fn simplify(&mut self) {
+        let Some(facts) = self.propagate_units() else {
+            return;
+        };
+        let old_clauses = mem::take(&mut self.clauses);
+        let old_lbd = mem::take(&mut self.lbd);
+        // each fact becomes its own reason, so the clauses that implied it
+        // can go
+        for lit in facts {
+            self.log_proof(false, &[lit]);
+            self.reasons[lit.var() as usize] = Some(self.clauses.len());
+            self.clauses.push(vec![lit]);
+            self.lbd.push(0);
+        }
+        for (mut clause, lbd) in old_clauses.into_iter().zip(old_lbd) {
+            if clause.iter().any(|&lit| self.val(lit) == Some(true)) {
+                self.log_proof(true, &clause);
+                continue;
+            }
+            if clause.iter().any(|&lit| self.val(lit).is_some()) {
+                let old = clause.clone();
+                clause.retain(|&lit| self.val(lit).is_none());
+                self.log_proof(false, &clause);
+                self.log_proof(true, &old);
+            }
+            self.clauses.push(clause);
+            self.lbd.push(lbd);
+        }
+        self.init_watches();
+        #[cfg(debug_assertions)]
+        self.check_watch_invariants();
+    }
+
+    /// The clauses given to `new()` and `add_clause()` as they were, before
+    /// preprocessing simplified, reordered or eliminated them, e.g. to write
+    /// the formula back out with `write_dimacs`.
+    pub fn original_clauses(&self) -> &[Clause] {
+        &self.original_clauses
+    }
+
+    /// Records the variables a projected model count is over, e.g. those of
+    /// `Dimacs::independent_support`, for tools built on the solver to read
+    /// back with `independent_support()`. The search itself ignores them.
+    pub fn set_independent_support(&mut self, vars: Vec<Var>) {
+        self.independent_support = vars;
+    }
+
+    /// The variables given to `set_independent_support`, empty by default.
+    pub fn independent_support(&self) -> &[Var] {
+        &self.independent_support
+    }
+
+    #[inline(always)]
+    pub fn val(&self, lit: Lit) -> Option<bool> {
+        match self.assignment.get(lit.var() as usize) {
+            Some(val) => {
+                if lit.is_positive() { 
+                    Some(val) 
+                } else { 
+                    Some(!val) 
+                }
+            }
+            None => None,
+        }
+    }
+    
+    #[allow(dead_code)]
+    fn pick_variable_two(&self) -> usize {
+        let mut best_var = 0;
+        let mut best_score = 0;
+        
+        for v in 1..=self.num_vars {
+            if self.assignment.get(v).is_none() {
+                let score = self.positives[v] + self.negatives[v];
+                if score > best_score {
+                    best_score = score;
+                    best_var = v;
+                }
+            }
+        }
+        
+        best_var
+    }
+    
+    fn pick_variable(&mut self) -> usize {
+        // heuristic picking variable appearing in most unresolved clauses
+        let mut scores = mem::take(&mut self.scores);
+        scores.fill(0);
+        for clause in &self.clauses {
+            let mut satisfied = false;
+            for &lit in clause {
+                if let Some(true) = self.val(lit) {
+                    satisfied = true;
+                    break;
+                }
+            }
+            if !satisfied {
+                for &lit in clause {
+                    let var = lit.var() as usize;
+                    if self.assignment.get(var).is_none() {
+                        scores[var] += 1;
+                    }
+                }
+            }
+        }
+        
+
+        let mut best_var = 0;
+        let mut best_score = 0;
+        let mut ties = 1;
+
+        for (v, &score) in scores.iter().enumerate().skip(1) {
+            if self.assignment.get(v).is_none() && score > best_score {
+                best_score = score;
+                best_var = v;
+                ties = 1;
+            } else if score == best_score && score > 0 {
+                // with a seed, each tied variable is kept with equal chance
+                if let Some(rng) = &mut self.rng {
+                    ties += 1;
+                    if rng.below(ties) == 0 {
+                        best_var = v;
+                    }
+                }
+            }
+        }
+        self.scores = scores;
+
+        best_var
+    }
+
+    // Lookahead branching: assigns each candidate variable both ways on a
+    // trial level, propagates, and counts the assignments each value implies.
+    // A value that runs into a conflict is a failed literal, so its variable
+    // is picked at once with the other value as its phase. Trial assignments
+    // are undone and the saved phases restored before returning.
+    fn pick_variable_lookahead(&mut self) -> usize {
+        let mut candidates: Vec<usize> = (1..=self.num_vars)
+            .filter(|&v| self.assignment.get(v).is_none() && !self.eliminated[v])
+            .collect();
+        if candidates.len() > LOOKAHEAD_CANDIDATES {
+            candidates.sort_by_key(|&v| Reverse(self.positives[v] + self.negatives[v]));
+            candidates.truncate(LOOKAHEAD_CANDIDATES);
+        }
+
+        // the probes are no part of the search, so they leave the saved
+        // phases, the propagation count and the agility as they were
+        let phases = self.phases.clone();
+        let (propagations, agility) = (self.stats.propagations, self.stats.agility);
+        let level = self.decision_level();
+        let mut best_var = candidates.first().copied().unwrap_or(0);
+        let mut best_score = 0;
+        let mut failed = None;
+        for var in candidates {
+            let mut implied = [0; 2];
+            for (i, positive) in [true, false].into_iter().enumerate() {
+                let start = self.history.len();
+                self.trail_lim.push(start);
+                self.enqueue(Lit::new(var as Var, positive), None);
+                let conflict = self.bcp();
+                implied[i] = self.history.len() - start;
+                self.backtrack_to_level(level);
+                if conflict.is_some() {
+                    failed = Some((var, !positive));
+                    break;
+                }
+            }
+            if failed.is_some() {
+                break;
+            }
+            let score = LOOKAHEAD_WEIGHT * implied[0] * implied[1] + implied[0] + implied[1];
+            if score > best_score {
+                best_score = score;
+                best_var = var;
+            }
+        }
+        self.phases = phases;
+        self.stats.propagations = propagations;
+        self.stats.agility = agility;
+
+        if let Some((var, value)) = failed {
+            self.phases[var] = Some(value);
+            return var;
+        }
+        best_var
+    }
+
+    /// Selects the branching heuristic for subsequent solves.
+    pub fn set_heuristic(&mut self, heuristic: Heuristic) {
+        self.config.heuristic = heuristic;
+    }
+
+    /// Seeds the saved phases with `(variable, value)` hints, e.g. from a
+    /// previous near-solution: the next decision on a hinted variable tries
+    /// the hinted value first. Unlike assumptions the hints commit to
+    /// nothing, and like any saved phase they are overwritten once the
+    /// search assigns the variable. `reset()` clears them. Hints on
+    /// variables the formula doesn't have are ignored.
+    pub fn set_phase_hints(&mut self, hints: &[(Var, bool)]) {
+        for &(var, value) in hints {
+            if let Some(phase) = self.phases.get_mut(var as usize) {
+                *phase = Some(value);
+            }
+        }
+    }
+
+    fn pick_branch_var(&mut self) -> usize {
+        match self.config.heuristic {
+            // variables only in XOR constraints have no occurrences to score
+            Heuristic::Occurrences => match self.pick_variable() {
+                0 => self
+                    .xors
+                    .variables()
+                    .iter()
+                    .copied()
+                    .find(|&var| self.assignment.get(var).is_none())
+                    .unwrap_or(0),
+                var => var,
+            },
+            Heuristic::Vsids => {
+                while let Some(var) = self.order.pop(&self.activity) {
+                    if self.assignment.get(var).is_none() {
+                        return var;
+                    }
+                }
+                0
+            }
+            Heuristic::Lookahead => self.pick_variable_lookahead(),
+        }
+    }
+
+    fn bump_activity(&mut self, var: usize) {
+        self.activity[var] += self.var_inc;
+        if self.activity[var] > ACTIVITY_LIMIT {
+            for act in &mut self.activity {
+                *act /= ACTIVITY_LIMIT;
+            }
+            self.var_inc /= ACTIVITY_LIMIT;
+        }
+        self.order.increased(var, &self.activity);
+    }
+
+    /// Searches for a model, returning true if there is one. It can be called
+    /// again: every call starts over from the root level and keeps what
+    /// earlier calls learned. See `reset()` for a run independent of earlier
+    /// ones.
+    pub fn solve(&mut self) -> bool {
+        self.solve_with_limit(u64::MAX) == SolveResult::Sat
+    }
+
+    /// Like `solve()`, but gives up with `SolveResult::Unknown` after
+    /// `max_conflicts` conflicts. The search is unwound on giving up, so the
+    /// solver can be called again, e.g. with a larger limit.
+    pub fn solve_with_limit(&mut self, max_conflicts: u64) -> SolveResult {
+        self.conflict_budget = self.stats.conflicts.saturating_add(max_conflicts);
+        if self.proof.is_none() {
+            self.pending_proof = Vec::new();
+        }
+        let result = self.search();
+        self.last_result = result;
+        debug_assert!(result != SolveResult::Sat || self.verify_model());
+        result
+    }
+
+    /// Like `solve()`, but gives up with `SolveResult::Unknown` once it has
+    /// run for about `timeout`. The clock is only checked every few dozen
+    /// conflicts, so the search can overrun a little.
+    pub fn solve_with_timeout(&mut self, timeout: Duration) -> SolveResult {
+        self.solve_with_limits(u64::MAX, timeout)
+    }
+
+    /// Gives up at whichever of `solve_with_limit()`'s conflict limit and
+    /// `solve_with_timeout()`'s time limit is reached first.
+    pub fn solve_with_limits(&mut self, max_conflicts: u64, timeout: Duration) -> SolveResult {
+        self.deadline = Instant::now().checked_add(timeout);
+        let result = self.solve_with_limit(max_conflicts);
+        self.deadline = None;
+        result
+    }
+
+    fn out_of_time(&self) -> bool {
+        self.deadline.is_some_and(|deadline| {
+            self.stats.conflicts.is_multiple_of(DEADLINE_CHECK) && Instant::now() >= deadline
+        })
+    }
+
+    /// Makes the search poll `flag` once per conflict and give up with
+    /// `SolveResult::Unknown` when it is set, unwinding as on hitting a
+    /// conflict limit. The flag is left as it is, so later solves stop at
+    /// their first conflict until it is cleared.
+    pub fn set_interrupt(&mut self, flag: Arc<AtomicBool>) {
+        self.interrupt = Some(flag);
+    }
+
+    /// Registers `cb` to be called with every clause conflict analysis
+    /// learns, as soon as it is derived and before it is minimized, e.g. to
+    /// log it or share it with another solver. Replaces any earlier callback.
+    pub fn on_learn(&mut self, cb: LearnCallback) {
+        self.on_learn = Some(cb);
+    }
+
+    /// Registers `cb` to be called during a solve with how far the search
+    /// has got, at most once per `interval` and only at conflicts, e.g. to
+    /// print progress lines on long runs. Replaces any earlier callback.
+    pub fn on_progress(&mut self, interval: Duration, cb: ProgressCallback) {
+        self.on_progress = Some((interval, cb));
+        self.last_progress = Instant::now();
+    }
+
+    // Calls the on_progress() callback if its interval has passed, reading
+    // the clock as rarely as out_of_time() does.
+    fn report_progress(&mut self) {
+        let Some((interval, mut cb)) = self.on_progress.take() else {
+            return;
+        };
+        if self.stats.conflicts.is_multiple_of(DEADLINE_CHECK)
+            && self.last_progress.elapsed() >= interval
+        {
+            cb(&Progress {
+                stats: &self.stats,
+                learned_clauses: self.lbd.iter().filter(|&&lbd| lbd > 0).count(),
+                decision: self.current_decision(),
+            });
+            self.last_progress = Instant::now();
+        }
+        self.on_progress = Some((interval, cb));
+    }
+
+    // The literal decided on the current level. An assumption that was
+    // already implied leaves its level without one.
+    fn current_decision(&self) -> Option<Lit> {
+        let start = *self.trail_lim.last()?;
+        self.history
+            .get(start)
+            .copied()
+            .filter(|lit| self.reasons[lit.var() as usize].is_none())
+    }
+
+    fn interrupted(&self) -> bool {
+        self.interrupt
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// Solves with `assumptions` temporarily forced true. They are decided
+    /// before anything else, one per decision level, so everything learned
+    /// stays valid for later calls; the next call starts without them. `Unsat`
+    /// here can mean the formula is unsatisfiable only under these assumptions.
+    /// Variables above `num_vars` are added to the formula, as with
+    /// `with_temp_clause`.
+    pub fn solve_under_assumptions(&mut self, assumptions: &[Lit]) -> SolveResult {
+        self.use_vars(assumptions);
+        self.assumptions = assumptions.to_vec();
+        let result = self.solve_with_limit(u64::MAX);
+        self.assumptions.clear();
+        result
+    }
+
+    /// After `solve_under_assumptions` returned `Unsat`, the assumptions that
+    /// together made the formula unsatisfiable. It is a subset of the
+    /// assumptions but not necessarily a minimal one; it is empty when the
+    /// formula is unsatisfiable without any assumptions.
+    pub fn unsat_core(&self) -> Vec<Lit> {
+        self.core.clone()
+    }
+
+    /// Streams a DRAT proof to `out`: the clauses preprocessing derived and
+    /// deleted, then every clause learned from now on as an addition line,
+    /// and a refutation ends with the empty clause `0`, so an UNSAT answer
+    /// can be checked with e.g. drat-trim against the original formula.
+    /// Unsat results under assumptions or inside `with_temp_clause` are not
+    /// refutations and add no `0`.
+    ///
+    /// Set it before the first solve. Until then the solver holds a copy of
+    /// every clause preprocessing changed, so that the proof can start with
+    /// those steps; a solve without a proof sink frees them, since a proof
+    /// begun after it would lack the clauses learned there anyway.
+    pub fn set_proof(&mut self, out: Box<dyn Write>) {
+        self.proof = Some(out);
+        self.proof_error = None;
+        for (deleted, clause) in mem::take(&mut self.pending_proof) {
+            self.log_proof(deleted, &clause);
+        }
+    }
+
+    /// Flushes the proof sink, reporting the first error hit while writing
+    /// the proof; logging stops at that error.
+    pub fn flush_proof(&mut self) -> io::Result<()> {
+        if let Some(e) = self.proof_error.take() {
+            return Err(e);
+        }
+        match &mut self.proof {
+            Some(out) => out.flush(),
+            None => Ok(()),
+        }
+    }
+
+    // Writes `lits` as a DRAT addition line, or as a deletion line.
+    fn log_proof(&mut self, deleted: bool, lits: &[Lit]) {
+        let Some(out) = &mut self.proof else {
+            return;
+        };
+        let mut result = if deleted { write!(out, "d ") } else { Ok(()) };
+        for &lit in lits {
+            result = result.and_then(|_| write!(out, "{} ", lit));
+        }
+        if let Err(e) = result.and_then(|_| writeln!(out, "0")) {
+            self.proof = None;
+            self.proof_error = Some(e);
+        }
+    }
+
+    // Ends the proof with the empty clause.
+    fn refute(&mut self) -> SolveResult {
+        self.log_proof(false, &[]);
+        SolveResult::Unsat
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Returns the model found by the last `solve()`, or `None` if it was not
+    /// SAT (or nothing has been solved yet). The vector is indexed by variable, so
+    /// index 0 is unused; don't-care variables are reported as `true`.
+    pub fn model(&self) -> Option<Vec<bool>> {
+        if self.last_result != SolveResult::Sat {
+            return None;
+        }
+        Some(self.model.clone())
+    }
+
+    /// Re-checks the model of the last `solve()` against every original
+    /// clause and XOR constraint, as a guard against search and
+    /// preprocessing bugs. False if the last result wasn't SAT.
+    pub fn verify_model(&self) -> bool {
+        self.last_result == SolveResult::Sat
+            && check_model(&self.original_clauses, &self.model)
+            && self.xors.satisfied(&self.model)
+    }
+
+    fn save_model(&mut self) {
+        self.model = vec![false; self.num_vars + 1];
+        for v in 1..=self.num_vars {
+            self.model[v] = self.assignment.get(v).unwrap_or(true);
+        }
+
+        // An eliminated variable only has to be set true when one of its
+        // removed clauses with it positive is otherwise false, and false when
+        // one with it negative is; the resolvents rule out both at once.
+        for (pivot, clause) in self.elim_stack.iter().rev() {
+            let satisfied = clause.iter().any(|&lit| {
+                lit != *pivot && self.model[lit.var() as usize] == lit.is_positive()
+            });
+            if !satisfied {
+                self.model[pivot.var() as usize] = pivot.is_positive();
+            }
+        }
+    }
+
+    // Unit and empty clauses have no second watch to trigger them, so they
+    // are asserted here, which also catches contradicting units before any
+    // search; the root facts are then queued to be re-propagated in full in
+    // case clauses were added or retracted since the last solve. False if a
+    // clause is already falsified.
+    fn assert_units(&mut self) -> bool {
+        for idx in 0..self.clauses.len() {
+            let clause = &self.clauses[idx];
+            if clause.is_empty() {
+                return false;
+            }
+            if clause.len() == 1 && !self.enqueue(clause[0], Some(idx)) {
+                return false;
+            }
+        }
+        self.qhead = 0;
+        true
+    }
+
+    /// Runs unit propagation (and XOR propagation) at the root level and
+    /// returns every literal it forces, which holds in all models; None if
+    /// it derives a conflict, i.e. the formula is unsatisfiable. Variables
+    /// removed by preprocessing are not reported.
+    pub fn propagate_units(&mut self) -> Option<Vec<Lit>> {
+        self.backtrack_to_level(0);
+        if !self.assert_units() {
+            return None;
+        }
+        loop {
+            let conflict = match self.bcp() {
+                None if !self.xors.is_empty() => self.propagate_xors(),
+                conflict => conflict,
+            };
+            if conflict.is_some() {
+                return None;
+            }
+            if self.qhead == self.history.len() {
+                return Some(self.history.clone());
+            }
+        }
+    }
+
+    fn search(&mut self) -> SolveResult {
+        // Every solve starts from the root level facts
+        self.backtrack_to_level(0);
+        self.core.clear();
+
+        // nothing to satisfy: every assignment is a model
+        if self.clauses.is_empty() && self.xors.is_empty() && self.assumptions.is_empty() {
+            self.save_model();
+            return SolveResult::Sat;
+        }
+
+        if !self.assert_units() {
+            return self.refute();
+        }
+
+        let mut restarts = 0;
+        let mut restart_countdown = self.config.restart_base * luby(restarts);
+        // pure literals are looked for whenever the search is back at the root,
+        // i.e. just above the assumption levels
+        let mut check_pure = self.config.pure_literals;
+
+        loop {
+            let conflict = match self.bcp() {
+                None if !self.xors.is_empty() => self.propagate_xors(),
+                conflict => conflict,
+            };
+            if let Some(conflict) = conflict {
+                self.stats.conflicts += 1;
+                self.dump_conflict(conflict);
+                if self.decision_level() == 0 {
+                    return self.refute();
+                }
+                if self.stats.conflicts >= self.conflict_budget
+                    || self.interrupted()
+                    || self.out_of_time()
+                {
+                    self.backtrack_to_level(0);
+                    return SolveResult::Unknown;
+                }
+                self.report_progress();
+
+                let mut learnt = self.analyze(conflict);
+                if let Some(cb) = &mut self.on_learn {
+                    cb(&learnt);
+                }
+                self.minimize(&mut learnt);
+                let backjump_level = self.backjump_level(&mut learnt);
+                let lbd = self.compute_lbd(&learnt);
+                self.backtrack_to_level(backjump_level);
+                self.learn(learnt, lbd);
+
+                if self.config.reduce_interval > 0 && self.stats.conflicts >= self.reduce_at {
+                    self.reduce_db();
+                    self.reduce_gap += REDUCE_INC;
+                    self.reduce_at = self.stats.conflicts + self.reduce_gap;
+                }
+
+                let restart = match self.config.restarts {
+                    _ if self.config.restart_base == 0 => false,
+                    Restarts::Luby => {
+                        restart_countdown -= 1;
+                        restart_countdown == 0
+                    }
+                    Restarts::Lbd => self.lbd_spike(),
+                };
+                if restart {
+                    restarts += 1;
+                    restart_countdown = self.config.restart_base * luby(restarts);
+                    self.forget_recent_lbds();
+                    if !self.config.agility_brake || self.stats.agility >= AGILITY_LIMIT {
+                        self.stats.restarts += 1;
+                        self.backtrack_to_level(0);
+                        check_pure = self.config.pure_literals;
+                    }
+                }
+                continue;
+            }
+            // literals implied by the XOR constraints still need propagating
+            if self.qhead < self.history.len() {
+                continue;
+            }
+
+            if self.decision_level() < self.assumptions.len() {
+                let lit = self.assumptions[self.decision_level()];
+                match self.val(lit) {
+                    // already implied: an empty level keeps levels and
+                    // assumptions lined up
+                    Some(true) => self.trail_lim.push(self.history.len()),
+                    Some(false) => {
+                        self.analyze_final(lit);
+                        self.backtrack_to_level(0);
+                        return SolveResult::Unsat;
+                    }
+                    None => {
+                        self.trail_lim.push(self.history.len());
+                        self.enqueue(lit, None);
+                    }
+                }
+                continue;
+            }
+
+            if check_pure && self.decision_level() == self.assumptions.len() {
+                check_pure = false;
+                if !self.apply_pure_literals() {
+                    return self.refute();
+                }
+                continue;
+            }
+
+            let pick_var = self.pick_branch_var();
+
+            // Could not find an unassigned variable and therefore must be true
+            if pick_var == 0 {
+                self.save_model();
+                return SolveResult::Sat;
+            }
+
+            // variables never assigned yet fall back to their occurrence counts
+            let try_positive_first = self.phases[pick_var]
+                .unwrap_or(self.positives[pick_var] >= self.negatives[pick_var]);
+
+            let first_lit = Lit::new(pick_var as Var, try_positive_first);
+
+            self.stats.decisions += 1;
+            self.trail_lim.push(self.history.len());
+            self.enqueue(first_lit, None);
+        }
+    }
+
+    // Assigns every literal that is pure with respect to the current partial
+    // assignment: it occurs unassigned in some unsatisfied clause while its
+    // negation occurs unassigned in none. Setting it can't falsify anything, but
+    // it isn't implied either, so each one is made a decision on its own level
+    // and stays undoable. This scans the whole clause database, so the search
+    // only calls it at the root. Returns false if the current assignment
+    // already falsifies a clause.
+    fn apply_pure_literals(&mut self) -> bool {
+        // bit 0: seen positive, bit 1: seen negative
+        let mut polarity = vec![0u8; self.num_vars + 1];
+        for clause in &self.clauses {
+            if clause.iter().any(|&lit| self.val(lit) == Some(true)) {
+                continue;
+            }
+            let mut open = false;
+            for &lit in clause {
+                if self.val(lit).is_none() {
+                    open = true;
+                    polarity[lit.var() as usize] |= if lit.is_positive() { 1 } else { 2 };
+                }
+            }
+            if !open {
+                return false;
+            }
+        }
+
+        for (v, &seen) in polarity.iter().enumerate().skip(1) {
+            // either value may break an XOR constraint
+            if self.xors.contains(v) {
+                continue;
+            }
+            let lit = match seen {
+                1 => Lit::new(v as Var, true),
+                2 => Lit::new(v as Var, false),
+                _ => continue,
+            };
+            self.trail_lim.push(self.history.len());
+            self.enqueue(lit, None);
+        }
+        true
+    }
+
+    /// Number of decisions (assumptions and pure literals included) the
+    /// current assignment rests on. 0 between solves unless the last one
+    /// found a model, whose trail is left in place.
+    pub fn decision_level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    /// The assigned literals in the order they were assigned: root-level
+    /// facts first, then each decision followed by what it implied.
+    pub fn trail(&self) -> &[Lit] {
+        &self.history
+    }
+
+    // Derives the first-UIP clause from the falsified clause `conflict` by
+    // resolving backwards along the trail with the reasons of implied literals.
+    // Returns the learned clause with the asserting literal first.
+    fn analyze(&mut self, conflict: usize) -> Clause {
+        let level = self.decision_level();
+        let mut learnt: Clause = Vec::new();
+        let mut pending = 0;
+        let mut index = self.history.len();
+        let mut clause_idx = conflict;
+        let mut resolved: Option<Lit> = None;
+
+        loop {
+            for k in 0..self.clauses[clause_idx].len() {
+                let lit = self.clauses[clause_idx][k];
+                if Some(lit) == resolved {
+                    continue;
+                }
+                let var = lit.var() as usize;
+                if !self.seen[var] && self.levels[var] > 0 {
+                    self.seen[var] = true;
+                    self.bump_activity(var);
+                    if self.levels[var] == level {
+                        pending += 1;
+                    } else {
+                        learnt.push(lit);
+                    }
+                }
+            }
+
+            // next literal of the current level on the trail that took part
+            loop {
+                index -= 1;
+                if self.seen[self.history[index].var() as usize] {
+                    break;
+                }
+            }
+            let lit = self.history[index];
+            let var = lit.var() as usize;
+            self.seen[var] = false;
+            pending -= 1;
+            if pending == 0 {
+                learnt.insert(0, lit.negate());
+                break;
+            }
+            resolved = Some(lit);
+            clause_idx = self.reasons[var].expect("implied literal without a reason");
+        }
+
+        for &lit in &learnt[1..] {
+            self.seen[lit.var() as usize] = false;
+        }
+        self.var_inc /= self.config.var_decay;
+        learnt
+    }
+
+    // Drops the literals of the learned clause `learnt` that the others imply:
+    // those whose reasons, followed back recursively, only lead to literals
+    // of the clause and root-level facts. The asserting literal stays first.
+    fn minimize(&mut self, learnt: &mut Clause) {
+        let mut marked = Vec::with_capacity(learnt.len());
+        let mut levels = 0;
+        for &lit in &learnt[1..] {
+            let var = lit.var() as usize;
+            self.seen[var] = true;
+            marked.push(var);
+            levels |= abstract_level(self.levels[var]);
+        }
+
+        let mut kept = 1;
+        for i in 1..learnt.len() {
+            let lit = learnt[i];
+            let implied = self.reasons[lit.var() as usize].is_some();
+            if !implied || !self.redundant(lit, levels, &mut marked) {
+                learnt[kept] = lit;
+                kept += 1;
+            }
+        }
+        learnt.truncate(kept);
+
+        for var in marked {
+            self.seen[var] = false;
+        }
+    }
+
+    // Whether the implied literal `lit` follows from the literals marked seen.
+    // Its reasons are explored depth-first; a literal reached on a level
+    // outside `levels` (the abstract levels of the clause) can't follow from
+    // the clause, which stops the search early. Literals shown to follow are
+    // marked too, and pushed to `marked` to be cleared by the caller.
+    fn redundant(&mut self, lit: Lit, levels: u64, marked: &mut Vec<usize>) -> bool {
+        let top = marked.len();
+        let mut stack = vec![lit];
+        while let Some(lit) = stack.pop() {
+            let reason = self.reasons[lit.var() as usize].unwrap();
+            for k in 0..self.clauses[reason].len() {
+                let other = self.clauses[reason][k];
+                let var = other.var() as usize;
+                if var == lit.var() as usize || self.seen[var] || self.levels[var] == 0 {
+                    continue;
+                }
+                if self.reasons[var].is_some() && abstract_level(self.levels[var]) & levels != 0 {
+                    self.seen[var] = true;
+                    marked.push(var);
+                    stack.push(other);
+                } else {
+                    for var in marked.drain(top..) {
+                        self.seen[var] = false;
+                    }
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    // Moves a literal of the highest level below the asserting one to the
+    // second position of `learnt`, so the two get watched, and returns that
+    // level: the level to backjump to.
+    fn backjump_level(&self, learnt: &mut Clause) -> usize {
+        let mut backjump_level = 0;
+        for i in 1..learnt.len() {
+            let lit_level = self.levels[learnt[i].var() as usize];
+            if lit_level > backjump_level {
+                backjump_level = lit_level;
+                learnt.swap(1, i);
+            }
+        }
+        backjump_level
+    }
+
+    // Collects into `core` the assumption `failed`, which is false under the
+    // earlier assumptions, plus the assumptions its negation was implied from.
+    fn analyze_final(&mut self, failed: Lit) {
+        self.core.push(failed);
+        if self.decision_level() == 0 {
+            return;
+        }
+
+        self.seen[failed.var() as usize] = true;
+        for i in (self.trail_lim[0]..self.history.len()).rev() {
+            let lit = self.history[i];
+            let var = lit.var() as usize;
+            if !self.seen[var] {
+                continue;
+            }
+            match self.reasons[var] {
+                // decisions below the current level are all assumptions
+                None => self.core.push(lit),
+                Some(reason) => {
+                    for &other in &self.clauses[reason] {
+                        let other_var = other.var() as usize;
+                        if other_var != var && self.levels[other_var] > 0 {
+                            self.seen[other_var] = true;
+                        }
+                    }
+                }
+            }
+            self.seen[var] = false;
+        }
+        self.seen[failed.var() as usize] = false;
+    }
+
+    // Number of distinct decision levels among the literals of `clause`.
+    fn compute_lbd(&self, clause: &[Lit]) -> u32 {
+        let mut levels: Vec<usize> = clause
+            .iter()
+            .map(|lit| self.levels[lit.var() as usize])
+            .collect();
+        levels.sort_unstable();
+        levels.dedup();
+        levels.len() as u32
+    }
+
+    // Whether the last LBD_WINDOW learned clauses have an average LBD high
+    // enough above the long-run one to restart (never before the window has
+    // filled up again since the last restart).
+    fn lbd_spike(&self) -> bool {
+        if self.recent_lbds.len() < LBD_WINDOW {
+            return false;
+        }
+        let recent = self.recent_lbd_sum as f64 / LBD_WINDOW as f64;
+        recent * LBD_MARGIN > self.stats.average_lbd()
+    }
+
+    fn forget_recent_lbds(&mut self) {
+        self.recent_lbds.clear();
+        self.recent_lbd_sum = 0;
+    }
+
+    // Adds a learned clause and assigns its asserting literal, which is unit
+    // after backjumping.
+    fn learn(&mut self, learnt: Clause, lbd: u32) {
+        let asserting = learnt[0];
+        self.stats.learned += 1;
+        self.stats.lbd_total += u64::from(lbd);
+        if self.recent_lbds.len() == LBD_WINDOW {
+            self.recent_lbd_sum -= u64::from(self.recent_lbds.pop_front().unwrap());
+        }
+        self.recent_lbds.push_back(lbd);
+        self.recent_lbd_sum += u64::from(lbd);
+        self.log_proof(false, &learnt);
+        let idx = self.push_clause(learnt, lbd);
+        self.enqueue(asserting, Some(idx));
+    }
+
+    // Deletes the worse half of the learned clauses, ranked by LBD and then
+    // length. Clauses with an LBD of at most 2 (which includes all learned
+    // units and binaries) are kept, as are reasons of current assignments and
+    // everything below `protected_len`. The remaining clauses are renumbered,
+    // so watches and reasons are remapped to the new indices.
+    fn reduce_db(&mut self) {
+        let mut locked = vec![false; self.clauses.len()];
+        for &lit in &self.history {
+            if let Some(reason) = self.reasons[lit.var() as usize] {
+                locked[reason] = true;
+            }
+        }
+        let mut candidates: Vec<usize> = (self.protected_len..self.clauses.len())
+            .filter(|&c| self.lbd[c] > 2 && !locked[c])
+            .collect();
+        candidates.sort_by_key(|&c| (Reverse(self.lbd[c]), Reverse(self.clauses[c].len())));
+
+        let mut deleted = vec![false; self.clauses.len()];
+        for &c in &candidates[..candidates.len() / 2] {
+            deleted[c] = true;
+        }
+
+        // kept clauses slide down over the deleted ones, keeping their order
+        let mut remap = vec![usize::MAX; self.clauses.len()];
+        let mut kept = 0;
+        for c in 0..self.clauses.len() {
+            if deleted[c] {
+                let clause = mem::take(&mut self.clauses[c]);
+                self.log_proof(true, &clause);
+                self.stats.deleted += 1;
+                continue;
+            }
+            self.clauses.swap(kept, c);
+            self.watches.swap(kept, c);
+            self.lbd.swap(kept, c);
+            remap[c] = kept;
+            kept += 1;
+        }
+        self.clauses.truncate(kept);
+        self.watches.truncate(kept);
+        self.lbd.truncate(kept);
+
+        for list in &mut self.watch_lists {
+            list.retain_mut(|c| {
+                *c = remap[*c];
+                *c != usize::MAX
+            });
+        }
+        for list in &mut self.binary_lists {
+            for (_, c) in list.iter_mut() {
+                *c = remap[*c];
+            }
+        }
+        for &lit in &self.history {
+            let reason = &mut self.reasons[lit.var() as usize];
+            if let Some(c) = reason {
+                *c = remap[*c];
+            }
+        }
+    }
+
+    // Drains the propagation queue through the watch lists, propagating each
+    // queued literal in turn, including those implied along the way, until
+    // nothing is left to propagate. Returns the index of a falsified clause,
+    // if unit propagation runs into one; the rest of the queue is then left
+    // unpropagated for backtracking to discard.
+    fn bcp(&mut self) -> Option<usize> {
+        while self.qhead < self.history.len() {
+            let lit = self.history[self.qhead];
+            self.qhead += 1;
+            self.stats.propagations += 1;
+            let conflict = self.propagate(lit);
+            // a full check per propagation is too slow for every debug build
+            #[cfg(all(debug_assertions, feature = "check-watches"))]
+            self.check_watch_invariants();
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    // Assigns `lit` and queues it for propagation. Returns false if `lit` is
+    // already false; if it is already true there is nothing to do, since it
+    // was queued when it was assigned.
+    fn enqueue(&mut self, lit: Lit, reason: Option<usize>) -> bool {
+        if let Some(val) = self.val(lit) {
+            return val;
+        }
+        self.assign_lit(lit, reason);
+        true
+    }
+
+    // Implies the other literal of every binary clause with -lit, then visits
+    // the longer clauses watching -lit now that lit is true. Each moves its watch
+    // to another non-false literal if it can; otherwise it is unit and its other
+    // watch gets assigned, or it is falsified and returned as the conflict.
+    // Only the clauses of -lit are visited: implied literals are enqueued, not
+    // propagated, so a conflict further down the chain is found when bcp()
+    // gets to them. Only bcp() calls this.
+    fn propagate(&mut self, lit: Lit) -> Option<usize> {
+        let neg_lit = lit.negate();
+        let neg_idx = neg_lit.index();
+
+        // binary clauses first: the other literal is implied outright
+        for i in 0..self.binary_lists[neg_idx].len() {
+            let (other_lit, clause_idx) = self.binary_lists[neg_idx][i];
+            if !self.enqueue(other_lit, Some(clause_idx)) {
+                return Some(clause_idx);
+            }
+        }
+
+        let mut i = 0;
+        while i < self.watch_lists[neg_idx].len() {
+            let clause_idx = self.watch_lists[neg_idx][i];
+            let clause = &self.clauses[clause_idx];
+            if clause.len() == 1 {
+                return Some(clause_idx);
+            }
+
+            let (first, second) = self.watches[clause_idx];
+
+            let (current, other) = if clause[first] == neg_lit {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            let other_lit = clause[other];
+
+            if let Some(true) = self.val(other_lit) {
+                i += 1;
+                continue;
+            }
+
+            let mut new_watch = None;
+            for (j, &lit) in clause.iter().enumerate() {
+                if j != current && j != other && self.val(lit) != Some(false) {
+                    new_watch = Some((j, lit.index()));
+                    break;
+                }
+            }
+
+            if let Some((j, new_idx)) = new_watch {
+                if current == first {
+                    self.watches[clause_idx] = (j, second);
+                } else {
+                    self.watches[clause_idx] = (first, j);
+                }
+                self.watch_lists[neg_idx].swap_remove(i);
+                self.watch_lists[new_idx].push(clause_idx);
+                continue;
+            }
+
+            i += 1;
+            if !self.enqueue(other_lit, Some(clause_idx)) {
+                return Some(clause_idx);
+            }
+        }
+        None
+    }
+ 
+    // Undoes every decision level above `level`: the trail is popped back to
+    // where level + 1 began, clearing those assignments (their saved phases
+    // stay) and returning their variables to the branching heap, and the
+    // propagation queue head moves back with it. The assignments of `level`
+    // and below stay, as propagated as they were. Takes time in the number
+    // of assignments undone. This is how the search moves between levels;
+    // backtrack() on its own is only for root-level assignments.
+    fn backtrack_to_level(&mut self, level: usize) {
+        if self.decision_level() > level {
+            let start = self.trail_lim[level];
+            self.trail_lim.truncate(level);
+            self.backtrack(start);
+        }
+    }
+
+    // Pops the trail down to its first `saved_len` assignments, which must
+    // not cut into a decision level still open.
+    fn backtrack(&mut self, saved_len: usize) {
+        debug_assert!(self.trail_lim.last().is_none_or(|&start| start <= saved_len));
+        self.qhead = self.qhead.min(saved_len);
+        while self.history.len() > saved_len {
+            let lit = self.history.pop().unwrap();
+            let var_idx = lit.var() as usize;
+            self.assignment.clear(var_idx);
+            self.order.insert(var_idx, &self.activity);
+        }
+    }
+
+    #[inline(always)]
+    fn assign_lit(&mut self, lit: Lit, reason: Option<usize>) {
+        let var_idx = lit.var() as usize;
+        if self.assignment.get(var_idx).is_none() {
+            let val = lit.is_positive();
+            let flipped = self.phases[var_idx] == Some(!val);
+            self.stats.agility = self.stats.agility * AGILITY_DECAY
+                + if flipped { 1.0 - AGILITY_DECAY } else { 0.0 };
+            self.assignment.set(var_idx, val);
+            self.levels[var_idx] = self.decision_level();
+            self.reasons[var_idx] = reason;
+            self.phases[var_idx] = Some(val);
+            self.history.push(lit);
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::iter;
+    use std::rc::Rc;
+
+    use crate::testing::{brute_force_sat, clauses, lits, pigeonhole, random_3sat, SharedBuf};
+
+    #[test]
+    fn original_clauses_survive_preprocessing() {
+        let formula = clauses(&[&[1, -1, 2], &[1, 2, 2], &[-1, 2], &[1, 2, 3]]);
+        let mut solver = Solver::new(formula.clone(), 3);
+        assert_eq!(solver.original_clauses(), &formula[..]);
+        assert!(solver.clauses.len() < formula.len());
+        assert!(solver.solve());
+        assert!(solver.verify_model());
+
+        solver.add_clause(lits(&[3, 3]));
+        assert_eq!(solver.original_clauses().last(), Some(&lits(&[3, 3])));
+        solver.with_temp_clause(&lits(&[-2]), |solver| {
+            solver.add_clause(lits(&[-3, 1]));
+            assert_eq!(solver.original_clauses().len(), 6);
+        });
+        assert_eq!(solver.original_clauses().len(), 5);
+    }
+
+    #[test]
+    fn temp_clause_is_retracted() {
+        let mut solver = Solver::new(clauses(&[&[1, 2], &[-1, 2]]), 2);
+        let clauses = solver.clauses.clone();
+        let watches = solver.watches.clone();
+        let watch_lists = solver.watch_lists.clone();
+
+        assert!(!solver.with_temp_clause(&lits(&[-2]), |solver| solver.solve()));
+        assert_eq!(solver.clauses, clauses);
+        assert_eq!(solver.watches, watches);
+        assert_eq!(solver.watch_lists, watch_lists);
+        assert!(solver.history.is_empty());
+        assert!((1..=2).all(|var| solver.assignment.get(var).is_none()));
+        assert!(solver.solve());
+    }
+
+    #[test]
+    fn temp_clause_over_pure_and_new_variables() {
+        // 1 is pure in the formula, but not once (-1) is added
+        let mut solver = Solver::new(clauses(&[&[1, 2]]), 2);
+        assert!(solver.with_temp_clause(&lits(&[-1]), |solver| solver.solve()));
+        assert!(solver.with_temp_clause(&lits(&[3]), |solver| {
+            solver.solve() && solver.val(Lit::from(3)) == Some(true)
+        }));
+        assert!(solver.history.is_empty());
+        assert!(solver.solve());
+    }
+
+    #[test]
+    fn root_conflict_is_found_before_branching() {
+        // asserting the units and propagating them at the root refutes these,
+        // with at most the one root-level conflict
+        let formulas = [clauses(&[&[1], &[-1]]), clauses(&[&[1], &[-1, 2], &[-2, 3], &[-3]])];
+        for clauses in formulas {
+            let mut solver = Solver::new(clauses.clone(), 3);
+            assert!(!solver.solve(), "{:?}", clauses);
+            assert!(solver.stats.conflicts <= 1, "{:?}", clauses);
+            assert_eq!(solver.stats.decisions, 0, "{:?}", clauses);
+        }
+    }
+
+    #[test]
+    fn stats_count_the_search() {
+        let (formula, num_vars) = pigeonhole(4);
+        let mut solver = Solver::new(formula, num_vars);
+        let fresh = Stats {
+            agility: 1.0,
+            ..Stats::default()
+        };
+        assert_eq!(solver.stats(), &fresh);
+        assert!(!solver.solve());
+        let stats = solver.stats().clone();
+        assert!(stats.decisions > 0 && stats.propagations > 0);
+        assert!(stats.conflicts > 0 && stats.learned > 0);
+        // accumulated over solves
+        assert!(!solver.solve());
+        assert!(solver.stats().propagations >= stats.propagations);
+    }
+
+    #[test]
+    fn packed_assignment_matches_option_vec() {
+        let len = 200;
+        let mut packed = Assignment::new(len);
+        let mut plain: Vec<Option<bool>> = vec![None; len];
+        for step in 0..1000usize {
+            let var = step * 37 % len;
+            match step % 3 {
+                0 => {
+                    packed.set(var, step % 2 == 0);
+                    plain[var] = Some(step % 2 == 0);
+                }
+                1 => {
+                    packed.clear(var);
+                    plain[var] = None;
+                }
+                _ => {
+                    packed.set(var, true);
+                    plain[var] = Some(true);
+                }
+            }
+            assert!((0..len).all(|v| packed.get(v) == plain[v]));
+        }
+        packed.grow(300);
+        assert!((len..300).all(|v| packed.get(v).is_none()));
+    }
+
+    #[test]
+    fn compact_keeps_verdicts_and_releases_capacity() {
+        let mut solver = Solver::new(clauses(&[&[1, 2, 3], &[-1, 2], &[-2, 3]]), 3);
+        let queries: [&[i32]; 4] = [&[-3], &[-1, -2], &[1], &[-2, -3]];
+        let run = |solver: &mut Solver| -> Vec<bool> {
+            queries.iter().map(|q| solver.with_temp_clause(&lits(q), |s| s.solve())).collect()
+        };
+        let capacity = |solver: &Solver| {
+            solver.history.capacity()
+                + solver.clauses.capacity()
+                + solver.watches.capacity()
+                + solver.watch_lists.iter().map(Vec::capacity).sum::<usize>()
+        };
+
+        let before = run(&mut solver);
+        let peak = capacity(&solver);
+        solver.compact();
+        assert!(capacity(&solver) < peak);
+        assert_eq!(run(&mut solver), before);
+    }
+
+    #[test]
+    fn model_is_complete_and_satisfying() {
+        // variable 4 occurs in no clause
+        let formula = clauses(&[&[1, -2], &[2, 3], &[-1, -3]]);
+        let mut solver = Solver::new(formula.clone(), 4);
+        assert_eq!(solver.model(), None);
+        assert!(solver.solve());
+        let model = solver.model().unwrap();
+        assert_eq!(model.len(), 5);
+        for clause in &formula {
+            assert!(clause.iter().any(|&lit| model[lit.var() as usize] == lit.is_positive()));
+        }
+
+        let mut solver = Solver::new(clauses(&[&[1], &[-1]]), 1);
+        assert!(!solver.solve());
+        assert_eq!(solver.model(), None);
+    }
+
+    #[test]
+    fn conflict_limit_gives_up_and_resumes() {
+        let (formula, num_vars) = pigeonhole(6);
+        let mut solver = Solver::new(formula, num_vars);
+        assert_eq!(solver.solve_with_limit(5), SolveResult::Unknown);
+        assert!(solver.history.is_empty());
+        assert_eq!(solver.model(), None);
+        assert_eq!(solver.solve_with_limit(u64::MAX), SolveResult::Unsat);
+
+        let mut solver = Solver::new(clauses(&[&[1, 2], &[-1, 2], &[-2, 3]]), 3);
+        assert_eq!(solver.solve_with_limit(1), SolveResult::Sat);
+        assert!(solver.model().is_some());
+    }
+
+    #[test]
+    fn time_limit_gives_up_promptly() {
+        let (formula, num_vars) = pigeonhole(10);
+        let mut solver = Solver::new(formula, num_vars);
+        let start = Instant::now();
+        assert_eq!(solver.solve_with_timeout(Duration::from_millis(20)), SolveResult::Unknown);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(solver.history.is_empty());
+        assert_eq!(solver.deadline, None);
+
+        // the conflict limit still applies under a generous time limit
+        let conflicts = solver.stats().conflicts;
+        let result = solver.solve_with_limits(10, Duration::from_secs(60));
+        assert_eq!(result, SolveResult::Unknown);
+        assert_eq!(solver.stats().conflicts, conflicts + 10);
+
+        let (formula, num_vars) = pigeonhole(4);
+        let mut solver = Solver::new(formula, num_vars);
+        assert_eq!(solver.solve_with_timeout(Duration::from_secs(60)), SolveResult::Unsat);
+    }
+
+    #[test]
+    fn deep_searches_run_on_a_small_stack() {
+        // every pair (x, y) needs its own decision, so the search is 1500 levels
+        // deep; a recursive search would overflow the 128 KiB stack
+        let pairs = 1500;
+        let mut formula = Vec::new();
+        for pair in 0..pairs {
+            let (x, y) = (Lit::from(2 * pair + 1), Lit::from(2 * pair + 2));
+            formula.push(vec![x, y]);
+            formula.push(vec![-x, -y]);
+        }
+        let solve = move || {
+            let mut solver = Solver::new(formula.clone(), 2 * pairs as usize);
+            assert!(solver.solve());
+            let model = solver.model().unwrap();
+            for clause in &formula {
+                assert!(clause.iter().any(|&lit| model[lit.var() as usize] == lit.is_positive()));
+            }
+        };
+        let thread = std::thread::Builder::new().stack_size(128 * 1024).spawn(solve).unwrap();
+        thread.join().unwrap();
+    }
+
+    #[test]
+    fn learning_refutes_with_far_fewer_conflicts() {
+        // plain DPLL needed 17075 conflicts to refute this formula
+        let mut solver = Solver::new(random_3sat(150, 645, 6), 150);
+        assert!(!solver.solve());
+        assert!(solver.stats.conflicts < 17075 / 4, "{} conflicts", solver.stats.conflicts);
+
+        let (formula, num_vars) = pigeonhole(4);
+        assert!(!Solver::new(formula, num_vars).solve());
+    }
+
+    #[test]
+    fn heuristics_agree_on_verdicts() {
+        for seed in 0..20 {
+            let formula = random_3sat(30, 128, seed);
+            let mut vsids = Solver::new(formula.clone(), 30);
+            let mut occurrences = Solver::new(formula.clone(), 30);
+            occurrences.set_heuristic(Heuristic::Occurrences);
+            assert_eq!(vsids.solve(), occurrences.solve(), "seed {}", seed);
+        }
+    }
+
+    // Unit propagation by rescanning every clause until nothing changes; None
+    // if some clause ends up falsified.
+    fn naive_units(formula: &[Clause], num_vars: usize) -> Option<Vec<Option<bool>>> {
+        let mut values = vec![None; num_vars + 1];
+        let value = |values: &[Option<bool>], lit: Lit| {
+            values[lit.var() as usize].map(|v| v == lit.is_positive())
+        };
+        loop {
+            let mut changed = false;
+            for clause in formula {
+                if clause.iter().any(|&lit| value(&values, lit) == Some(true)) {
+                    continue;
+                }
+                let open: Vec<Lit> =
+                    clause.iter().copied().filter(|&lit| value(&values, lit).is_none()).collect();
+                match open[..] {
+                    [] => return None,
+                    [lit] => {
+                        values[lit.var() as usize] = Some(lit.is_positive());
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+            if !changed {
+                return Some(values);
+            }
+        }
+    }
+
+    #[test]
+    fn watched_bcp_matches_naive_propagation() {
+        let mut formulas = vec![
+            clauses(&[&[1], &[-1, 2], &[-2, -3, 4], &[3], &[-4, 5, 6], &[-5, -6]]),
+            clauses(&[&[-1, 2, 3], &[1], &[-2], &[-3, 4], &[-4, -1, 5], &[-5, 6, 7]]),
+            clauses(&[&[1], &[-1, 2], &[-2, 3], &[-3, -1]]),
+        ];
+        // random formulas plus enough units to propagate some way into them
+        for seed in 0..10 {
+            let mut formula = random_3sat(20, 40, seed);
+            let units = (1..=4).map(|var| Lit::new(var, (seed as Var ^ var) & 1 == 0));
+            formula.extend(units.map(|lit| vec![lit]));
+            formulas.push(formula);
+        }
+
+        for formula in formulas {
+            let mut solver = Solver::new(formula, 20);
+            // compare on the clauses as preprocessing left them
+            let formula = solver.clauses.clone();
+            let mut conflict = formula.iter().any(Vec::is_empty);
+            for idx in 0..solver.clauses.len() {
+                if solver.clauses[idx].len() == 1 {
+                    conflict |= !solver.enqueue(solver.clauses[idx][0], Some(idx));
+                }
+            }
+            let conflict = conflict || solver.bcp().is_some();
+            match naive_units(&formula, 20) {
+                None => assert!(conflict, "{:?}", formula),
+                Some(values) => {
+                    assert!(!conflict, "{:?}", formula);
+                    for (var, &value) in values.iter().enumerate().skip(1) {
+                        assert_eq!(solver.assignment.get(var), value, "{:?}", formula);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn literals_that_become_pure_are_fixed() {
+        // 3 occurs both ways, but (-3 1) is satisfied once 1 is, leaving 3 pure
+        let mut solver = Solver::new(clauses(&[&[-3, 1], &[3, 4], &[-4, 2], &[-2, 4, 5]]), 5);
+        assert!(solver.apply_pure_literals());
+        assert_eq!(solver.val(Lit::from(3)), None);
+        assert_eq!(solver.val(Lit::from(5)), Some(true));
+
+        solver.backtrack_to_level(0);
+        solver.enqueue(Lit::from(1), None);
+        assert_eq!(solver.bcp(), None);
+        assert!(solver.apply_pure_literals());
+        assert_eq!(solver.val(Lit::from(3)), Some(true));
+        // pure literals are decisions of their own, not root facts
+        assert!(solver.levels[3] > 0);
+        assert_eq!(solver.reasons[3], None);
+
+        let mut solver = Solver::new(clauses(&[&[1, 2], &[-1, 3]]), 3);
+        solver.enqueue(Lit::from(-1), None);
+        solver.enqueue(Lit::from(-2), None);
+        assert!(!solver.apply_pure_literals());
+    }
+
+    #[test]
+    fn assumptions_hold_for_one_solve() {
+        let mut solver = Solver::new(clauses(&[&[1, 2], &[-1, 3], &[-2, 3, 4]]), 4);
+        assert_eq!(solver.solve_under_assumptions(&lits(&[1, -4])), SolveResult::Sat);
+        assert!(solver.model().unwrap()[3]);
+        assert_eq!(solver.solve_under_assumptions(&lits(&[-3, -4])), SolveResult::Unsat);
+        assert!(solver.history.is_empty());
+        assert!(solver.solve());
+        // a variable the formula doesn't have yet
+        assert_eq!(solver.solve_under_assumptions(&lits(&[6, -1])), SolveResult::Sat);
+        assert!(solver.model().unwrap()[6]);
+        assert_eq!(solver.solve_under_assumptions(&lits(&[3, -3])), SolveResult::Unsat);
+        assert!(solver.solve());
+    }
+
+    #[test]
+    fn unsat_core_names_the_failed_assumptions() {
+        // 2 and 4 clash through (-2 5)(-5 -4); 1, 3 and 6 are irrelevant
+        let formula = clauses(&[&[-2, 5], &[-5, -4], &[1, 3], &[-3, 6, 7]]);
+        let mut solver = Solver::new(formula, 7);
+        let assumptions = lits(&[1, 2, 3, 4, 6]);
+        assert_eq!(solver.solve_under_assumptions(&assumptions), SolveResult::Unsat);
+        let core = solver.unsat_core();
+        assert!(core.contains(&Lit::from(2)) && core.contains(&Lit::from(4)), "{:?}", core);
+        assert!(core.iter().all(|lit| assumptions.contains(lit)), "{:?}", core);
+        assert_eq!(solver.solve_under_assumptions(&lits(&[1, 2, 3, 6])), SolveResult::Sat);
+        assert!(solver.unsat_core().is_empty());
+    }
+
+    // Checks that every lemma of a DRAT proof follows from the formula and the
+    // earlier lemmas by unit propagation, and that it ends with the empty
+    // clause. Deletions are skipped, which only makes the check more lenient.
+    fn check_rup_proof(formula: &[Clause], num_vars: usize, proof: &str) {
+        let mut known = formula.to_vec();
+        let lemmas: Vec<Clause> = proof
+            .lines()
+            .filter(|line| !line.starts_with("d "))
+            .map(|line| {
+                let lits: Vec<i32> = line.split_whitespace().map(|t| t.parse().unwrap()).collect();
+                assert_eq!(lits.last(), Some(&0), "{:?}", line);
+                lits[..lits.len() - 1].iter().map(|&lit| Lit::from(lit)).collect()
+            })
+            .collect();
+        assert_eq!(lemmas.last(), Some(&vec![]), "{}", proof);
+        for lemma in lemmas {
+            let mut negated = known.clone();
+            negated.extend(lemma.iter().map(|&lit| vec![-lit]));
+            assert!(naive_units(&negated, num_vars).is_none(), "{:?} is not RUP", lemma);
+            known.push(lemma);
+        }
+    }
+
+    #[test]
+    fn proof_of_unsat_checks_out() {
+        let (formula, num_vars) = pigeonhole(4);
+        let mut solver = Solver::new(formula.clone(), num_vars);
+        let proof = SharedBuf::default();
+        solver.set_proof(Box::new(proof.clone()));
+        assert!(!solver.solve());
+        solver.flush_proof().unwrap();
+        check_rup_proof(&formula, num_vars, &proof.contents());
+
+        // a root-level contradiction is refuted by the empty clause alone
+        let mut solver = Solver::new(clauses(&[&[1], &[-1]]), 1);
+        let proof = SharedBuf::default();
+        solver.set_proof(Box::new(proof.clone()));
+        assert!(!solver.solve());
+        assert_eq!(proof.contents().lines().last(), Some("0"));
+        check_rup_proof(&clauses(&[&[1], &[-1]]), 1, &proof.contents());
+    }
+
+    #[test]
+    fn proof_of_sat_has_no_empty_clause() {
+        let mut solver = Solver::new(random_3sat(30, 100, 3), 30);
+        let proof = SharedBuf::default();
+        solver.set_proof(Box::new(proof.clone()));
+        assert!(solver.solve());
+        assert!(proof.contents().lines().all(|line| line != "0"));
+
+        let mut solver = Solver::new(clauses(&[&[1, 2], &[-1, 2]]), 2);
+        let proof = SharedBuf::default();
+        solver.set_proof(Box::new(proof.clone()));
+        assert_eq!(solver.solve_under_assumptions(&lits(&[-2])), SolveResult::Unsat);
+        assert!(solver.with_temp_clause(&lits(&[-2]), |solver| !solver.solve()));
+        assert!(proof.contents().lines().all(|line| line != "0"));
+    }
+
+    #[test]
+    fn phase_saving_keeps_verdicts() {
+        for seed in 0..40 {
+            let formula = random_3sat(12, 52, seed);
+            let mut solver = Solver::new(formula.clone(), 12);
+            let sat = solver.solve();
+            assert_eq!(sat, brute_force_sat(&formula, 12), "seed {}", seed);
+            if sat {
+                // the saved phases are the model just found
+                let model = solver.model().unwrap();
+                assert!((1..=12).all(|var| solver.phases[var] == Some(model[var])));
+                assert!(solver.solve());
+            }
+        }
+    }
+
+    #[test]
+    fn tampered_models_fail_verification() {
+        let formula = clauses(&[&[1, -2], &[2, 3], &[-1, -3], &[2, -3]]);
+        let mut solver = Solver::new(formula.clone(), 3);
+        assert!(!solver.verify_model());
+        assert!(solver.solve());
+        assert!(solver.verify_model());
+        let model = solver.model().unwrap();
+        assert!(check_model(&formula, &model));
+
+        for var in 1..=3 {
+            let mut tampered = model.clone();
+            tampered[var] = !tampered[var];
+            assert!(!check_model(&formula, &tampered), "flipping {}", var);
+            solver.model = tampered;
+            assert!(!solver.verify_model());
+        }
+        // a model too short for the formula's variables fails as well
+        assert!(!check_model(&formula, &model[..2]));
+    }
+
+    #[test]
+    fn count_models_counts_free_variables() {
+        let formula = clauses(&[&[1, 2], &[-1, -2], &[2, 3]]);
+        let mut solver = Solver::new(formula.clone(), 3);
+        assert_eq!(solver.count_models(), 3);
+        // the formula is left as it was
+        assert!(solver.solve());
+        assert_eq!(solver.count_models(), 3);
+        // variable 4 is in no clause and doubles the count
+        assert_eq!(Solver::new(formula, 4).count_models(), 6);
+        assert_eq!(Solver::new(clauses(&[&[1], &[-1]]), 1).count_models(), 0);
+    }
+
+    #[test]
+    fn count_models_is_unchanged_by_elimination() {
+        let formula = clauses(&[&[1, 2], &[-1, 3], &[2, 3, 4]]);
+        assert_eq!(Solver::new(formula.clone(), 4).count_models(), 8);
+        assert_eq!(Solver::with_config(formula, 4, eliminating()).count_models(), 8);
+    }
+
+    #[test]
+    fn solutions_yields_each_model_once() {
+        let formula = clauses(&[&[1, 2], &[-1, -2], &[2, 3]]);
+        let expected = vec![
+            vec![false, false, true, false],
+            vec![false, false, true, true],
+            vec![false, true, false, true],
+        ];
+        let mut solver = Solver::new(formula, 3);
+        let mut models: Vec<Vec<bool>> = solver.solutions().collect();
+        models.sort();
+        assert_eq!(models, expected);
+        // the blocking clauses are gone again
+        assert!(solver.solve());
+        assert_eq!(solver.solutions().take(1).count(), 1);
+        assert_eq!(solver.solutions().count(), 3);
+    }
+
+    fn eliminating() -> SolverConfig {
+        SolverConfig {
+            eliminate: true,
+            ..SolverConfig::default()
+        }
+    }
+
+    #[test]
+    fn model_queries_see_through_elimination() {
+        let formula = clauses(&[&[1, 2], &[-1, 3], &[2, 3, 4]]);
+        let mut solver = Solver::with_config(formula.clone(), 4, eliminating());
+        assert!(solver.eliminated.iter().any(|&gone| gone));
+        assert_eq!(Solver::new(formula.clone(), 4).count_models(), 8);
+        assert_eq!(solver.count_models(), 8);
+        let models: Vec<Vec<bool>> = solver.solutions().collect();
+        assert_eq!(models.len(), 8);
+        assert!(models.iter().all(|model| check_model(&formula, model)));
+    }
+
+    #[test]
+    #[should_panic(expected = "removed by variable elimination")]
+    fn eliminated_variables_cannot_be_assumed() {
+        let formula = clauses(&[&[1, 2], &[-1, 3], &[2, 3, 4]]);
+        let mut solver = Solver::with_config(formula, 4, eliminating());
+        let var = (1..=4).find(|&var| solver.eliminated[var]).unwrap();
+        solver.solve_under_assumptions(&[Lit::new(var as Var, true)]);
+    }
+
+    #[test]
+    fn non_default_configs_keep_verdicts() {
+        let configs = [
+            SolverConfig {
+                var_decay: 1.0,
+                restart_base: 0,
+                pure_literals: false,
+                seed: 42,
+                ..SolverConfig::default()
+            },
+            SolverConfig {
+                var_decay: 0.5,
+                restart_base: 3,
+                eliminate: true,
+                ..SolverConfig::default()
+            },
+        ];
+        for seed in 0..20 {
+            let formula = random_3sat(14, 60, seed);
+            let expected = brute_force_sat(&formula, 14);
+            for config in &configs {
+                let mut solver = Solver::with_config(formula.clone(), 14, config.clone());
+                assert_eq!(solver.solve(), expected, "seed {} with {:?}", seed, config);
+                if expected {
+                    assert!(check_model(&formula, &solver.model().unwrap()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_search() {
+        let formula = random_3sat(60, 255, 11);
+        let run = |seed: u64| {
+            let config = SolverConfig {
+                seed,
+                ..SolverConfig::default()
+            };
+            let mut solver = Solver::with_config(formula.clone(), 60, config);
+            let sat = solver.solve();
+            (sat, solver.stats().clone())
+        };
+        for seed in [0, 1, 7] {
+            assert_eq!(run(seed), run(seed), "seed {}", seed);
+        }
+        // some seed breaks the ties differently from no seed at all
+        let unseeded = run(0);
+        assert!((1..10).any(|seed| run(seed).1 != unseeded.1));
+        assert!((1..10).all(|seed| run(seed).0 == unseeded.0));
+    }
+
+    #[test]
+    fn binary_chains_propagate_in_full() {
+        // 1 -> 2 -> ... -> 30 as binary clauses, given out of order; a unit (1)
+        // would already be pushed through the chain by self-subsumption
+        let formula: Vec<Clause> = (1..30).rev().map(|v| lits(&[-v, v + 1])).collect();
+        let mut solver = Solver::new(formula, 30);
+        assert!(solver.binary_lists.iter().filter(|list| !list.is_empty()).count() == 58);
+        assert_eq!(solver.solve_under_assumptions(&lits(&[1])), SolveResult::Sat);
+        assert!(solver.model().unwrap()[1..].iter().all(|&value| value));
+        assert_eq!(solver.stats().decisions, 0);
+        assert_eq!(solver.solve_under_assumptions(&lits(&[1, -30])), SolveResult::Unsat);
+
+        // a binary temporary clause over a new variable joins the chain
+        assert!(!solver.with_temp_clause(&lits(&[-30, 31]), |solver| {
+            solver.solve_under_assumptions(&lits(&[1, -31])) == SolveResult::Sat
+        }));
+        assert!(solver.solve());
+    }
+
+    #[test]
+    fn bcp_follows_implications_to_a_conflict() {
+        // under -5, 1 implies 2 and -3, and 2 implies 3
+        let formula = clauses(&[&[-1, 2, 5], &[-2, 3, 5], &[-3, -1, 5]]);
+        let mut solver = Solver::new(formula, 5);
+        solver.trail_lim.push(solver.history.len());
+        assert!(solver.enqueue(Lit::from(-5), None));
+        assert_eq!(solver.bcp(), None);
+        solver.trail_lim.push(solver.history.len());
+        assert!(solver.enqueue(Lit::from(1), None));
+
+        // propagating 1 only enqueues its implications; the conflict comes
+        // from propagating 2 in turn
+        solver.qhead += 1;
+        assert_eq!(solver.propagate(Lit::from(1)), None);
+        assert_eq!(solver.val(Lit::from(2)), Some(true));
+        assert_eq!(solver.val(Lit::from(3)), Some(false));
+        let conflict = solver.bcp().unwrap();
+        assert_eq!(solver.clauses[conflict].len(), 3);
+        assert!(solver.clauses[conflict].contains(&Lit::from(-2)));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn watches_stay_consistent_through_search() {
+        // many watch moves: learning, restarts, reduction and temp clauses
+        let config = SolverConfig {
+            reduce_interval: 50,
+            ..SolverConfig::default()
+        };
+        let (formula, num_vars) = pigeonhole(5);
+        let mut solver = Solver::with_config(formula, num_vars, config.clone());
+        assert!(!solver.solve());
+        solver.check_watch_invariants();
+        for seed in 0..5 {
+            let mut solver = Solver::with_config(random_3sat(40, 170, seed), 40, config.clone());
+            solver.check_watch_invariants();
+            solver.solve();
+            solver.check_watch_invariants();
+            solver.with_temp_clause(&lits(&[1, -2, 41]), |solver| {
+                solver.solve();
+                solver.check_watch_invariants();
+            });
+            solver.check_watch_invariants();
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "wrong watch lists")]
+    fn moved_watch_is_caught() {
+        let mut solver = Solver::new(clauses(&[&[1, 2, 3], &[-1, -2, -3]]), 3);
+        let (first, second) = solver.watches[0];
+        solver.watches[0] = (3 - first - second, second);
+        solver.check_watch_invariants();
+    }
+
+    fn looking_ahead() -> SolverConfig {
+        SolverConfig {
+            heuristic: Heuristic::Lookahead,
+            ..SolverConfig::default()
+        }
+    }
+
+    #[test]
+    fn lookahead_keeps_verdicts_in_fewer_decisions() {
+        for seed in 0..20 {
+            let formula = random_3sat(12, 55, seed);
+            let mut solver = Solver::with_config(formula.clone(), 12, looking_ahead());
+            assert_eq!(solver.solve(), brute_force_sat(&formula, 12), "seed {}", seed);
+            assert!(solver.verify_model() || solver.model().is_none());
+        }
+
+        // pigeonhole formulas reward branching on the most constrained pigeon
+        let (formula, num_vars) = pigeonhole(6);
+        let mut vsids = Solver::new(formula.clone(), num_vars);
+        let mut lookahead = Solver::with_config(formula, num_vars, looking_ahead());
+        assert!(!vsids.solve());
+        assert!(!lookahead.solve());
+        let decisions = (vsids.stats().decisions, lookahead.stats().decisions);
+        assert!(decisions.1 < decisions.0, "{:?}", decisions);
+    }
+
+    #[test]
+    fn lookahead_probes_leave_agility_alone() {
+        // every probe assigns against the saved phases, which would count as
+        // flips; restored, the agility only reflects the search itself
+        let (formula, num_vars) = pigeonhole(4);
+        let mut solver = Solver::with_config(formula, num_vars, looking_ahead());
+        solver.set_phase_hints(&(1..=num_vars as Var).map(|var| (var, false)).collect::<Vec<_>>());
+        let before = solver.stats().agility;
+        solver.trail_lim.push(solver.history.len());
+        solver.pick_variable_lookahead();
+        assert_eq!(solver.stats().agility, before);
+        assert_eq!(solver.stats().propagations, 0);
+    }
+
+    #[test]
+    fn lookahead_probes_are_not_propagations() {
+        // a chain of implications that never conflicts
+        let formula: Vec<Clause> = (1..20).map(|var| lits(&[-var, var + 1])).collect();
+        let mut solver = Solver::with_config(formula, 20, looking_ahead());
+        assert!(solver.solve());
+        assert_eq!(solver.stats().conflicts, 0);
+        // each variable is assigned and propagated once
+        assert!(solver.stats().propagations <= 20);
+    }
+
+    #[test]
+    fn learn_callback_sees_every_learned_clause() {
+        let (formula, num_vars) = pigeonhole(3);
+        let learned = Rc::new(RefCell::new(Vec::new()));
+        let mut solver = Solver::new(formula.clone(), num_vars);
+        let sink = learned.clone();
+        solver.on_learn(Box::new(move |clause| sink.borrow_mut().push(clause.to_vec())));
+        assert!(!solver.solve());
+
+        let learned = learned.borrow();
+        assert!(!learned.is_empty());
+        assert_eq!(learned.len() as u64, solver.stats().learned);
+        // each follows from the formula: adding its negation leaves no model
+        for clause in learned.iter() {
+            let mut refuted = formula.clone();
+            refuted.extend(clause.iter().map(|&lit| vec![-lit]));
+            assert!(!brute_force_sat(&refuted, num_vars), "{:?}", clause);
+        }
+    }
+
+    #[test]
+    fn progress_is_reported_at_most_once_per_interval() {
+        let (formula, num_vars) = pigeonhole(8);
+        let count = |interval: Duration| {
+            let seen = Rc::new(RefCell::new(Vec::new()));
+            let mut solver = Solver::new(formula.clone(), num_vars);
+            let sink = seen.clone();
+            solver.on_progress(
+                interval,
+                Box::new(move |progress| sink.borrow_mut().push(progress.stats.conflicts)),
+            );
+            assert_eq!(solver.solve_with_limit(300), SolveResult::Unknown);
+            seen.take()
+        };
+        // without a wait, at every conflict the clock is read at
+        let seen = count(Duration::ZERO);
+        assert!(!seen.is_empty());
+        assert!(seen.iter().all(|conflicts| conflicts.is_multiple_of(DEADLINE_CHECK)));
+        assert!(count(Duration::from_secs(3600)).is_empty());
+    }
+
+    #[test]
+    fn minimization_shortens_learned_clauses() {
+        // the callback sees each clause before minimization, and with no
+        // reduction the minimized ones stay at the end of the clause list
+        let keeping = SolverConfig {
+            reduce_interval: u64::MAX,
+            ..SolverConfig::default()
+        };
+        let check = |formula: Vec<Clause>, num_vars: usize, brute_force: bool| {
+            let raw = Rc::new(RefCell::new(Vec::new()));
+            let mut solver = Solver::with_config(formula.clone(), num_vars, keeping.clone());
+            let sink = raw.clone();
+            solver.on_learn(Box::new(move |clause| sink.borrow_mut().push(clause.to_vec())));
+            let num_clauses = solver.clauses.len();
+            solver.solve();
+
+            let raw = raw.borrow();
+            let minimized = &solver.clauses[num_clauses..];
+            assert_eq!(raw.len(), minimized.len());
+            for (raw, minimized) in raw.iter().zip(minimized) {
+                assert_eq!(raw[0], minimized[0]);
+                assert!(minimized.iter().all(|lit| raw.contains(lit)));
+                if brute_force {
+                    let mut refuted = formula.clone();
+                    refuted.extend(minimized.iter().map(|&lit| vec![-lit]));
+                    assert!(!brute_force_sat(&refuted, num_vars), "{:?}", minimized);
+                }
+            }
+            let lengths = |clauses: &[Clause]| clauses.iter().map(Vec::len).sum::<usize>();
+            (lengths(&raw), lengths(minimized))
+        };
+
+        let (raw, minimized) = check(random_3sat(14, 70, 2), 14, true);
+        assert!(minimized <= raw);
+        let (formula, num_vars) = pigeonhole(6);
+        let (raw, minimized) = check(formula, num_vars, false);
+        assert!(minimized < raw, "{} {}", raw, minimized);
+    }
+
+    #[test]
+    fn agility_brake_keeps_verdicts() {
+        let braking = |restarts| SolverConfig {
+            agility_brake: true,
+            restarts,
+            restart_base: 10,
+            ..SolverConfig::default()
+        };
+        for restarts in [Restarts::Lbd, Restarts::Luby] {
+            for seed in 0..10 {
+                let formula = random_3sat(14, 62, seed);
+                let mut solver = Solver::with_config(formula.clone(), 14, braking(restarts));
+                assert_eq!(solver.solve(), brute_force_sat(&formula, 14), "seed {}", seed);
+                let agility = solver.stats().agility;
+                assert!((0.0..=1.0).contains(&agility), "{}", agility);
+            }
+            let (formula, num_vars) = pigeonhole(5);
+            assert!(!Solver::with_config(formula, num_vars, braking(restarts)).solve());
+        }
+    }
+
+    #[test]
+    fn clauses_from_a_generator_solve_as_a_vec_does() {
+        for seed in 0..5 {
+            let formula = random_3sat(30, 128, seed);
+            let mut clauses = formula.clone().into_iter();
+            let generated = iter::from_fn(|| clauses.next());
+            let mut streamed = Solver::from_clauses(generated, 30);
+            let mut collected = Solver::new(formula, 30);
+            assert_eq!(streamed.solve(), collected.solve());
+            assert_eq!(streamed.model(), collected.model());
+            assert_eq!(streamed.stats(), collected.stats());
+        }
+        let mut adapted = Solver::from_clauses((1..=5).map(|var| lits(&[-var, var + 1])), 6);
+        assert!(adapted.solve_under_assumptions(&lits(&[-6, 1])) == SolveResult::Unsat);
+    }
+
+    #[test]
+    fn raw_solvers_agree_with_preprocessed_ones() {
+        let mut formulas: Vec<(Vec<Clause>, usize)> = (0..10)
+            .map(|seed| (random_3sat(12, 52, seed), 12))
+            .collect();
+        formulas.push(pigeonhole(4));
+        formulas.push((clauses(&[&[1, 2, 3], &[1, 2], &[-1, 2], &[2, -2, 4], &[3, 3]]), 4));
+        for (formula, num_vars) in formulas {
+            let expected = brute_force_sat(&formula, num_vars);
+            let mut raw = Solver::new_raw(formula.clone(), num_vars);
+            // only the tautology goes, and nothing is reordered
+            let kept: Vec<Clause> = formula
+                .iter()
+                .filter(|clause| !clause.iter().any(|&lit| clause.contains(&-lit)))
+                .cloned()
+                .collect();
+            assert_eq!(raw.clauses.len(), kept.len());
+            assert_eq!(raw.solve(), expected);
+            if expected {
+                assert!(check_model(&formula, &raw.model().unwrap()));
+            }
+
+            // preprocessing afterwards, even twice, keeps the verdict
+            raw.preprocess();
+            let preprocessed = raw.clauses.clone();
+            raw.preprocess();
+            assert_eq!(raw.clauses, preprocessed);
+            #[cfg(debug_assertions)]
+            raw.check_watch_invariants();
+            assert_eq!(raw.solve(), expected);
+            assert_eq!(Solver::new(formula, num_vars).solve(), expected);
+        }
+    }
+
+    #[test]
+    fn backtracking_keeps_the_lower_levels() {
+        // a unit at the root, then three decisions that each imply one more
+        let formula = clauses(&[&[1], &[-1, 2, 9], &[-3, 4], &[-5, 6], &[-7, 8], &[3, 5, 7, 9]]);
+        let mut solver = Solver::new_raw(formula, 9);
+        assert!(solver.assert_units());
+        assert_eq!(solver.bcp(), None);
+        for decision in [-9, 3, 5, 7] {
+            if decision > 0 {
+                solver.trail_lim.push(solver.history.len());
+            }
+            assert!(solver.enqueue(Lit::from(decision), None));
+            assert_eq!(solver.bcp(), None);
+        }
+        assert_eq!(solver.decision_level(), 3);
+        let levels: Vec<(Lit, usize)> = solver
+            .history
+            .iter()
+            .map(|&lit| (lit, solver.levels[lit.var() as usize]))
+            .collect();
+
+        solver.backtrack_to_level(1);
+        let kept: Vec<(Lit, usize)> = levels.iter().copied().filter(|&(_, l)| l <= 1).collect();
+        assert_eq!(solver.decision_level(), 1);
+        assert_eq!(solver.history, kept.iter().map(|&(lit, _)| lit).collect::<Vec<_>>());
+        assert_eq!(solver.qhead, solver.history.len());
+        for (lit, level) in levels {
+            let expected = (level <= 1).then_some(true);
+            assert_eq!(solver.val(lit), expected, "{} at level {}", lit, level);
+        }
+        assert_eq!(solver.val(Lit::from(4)), Some(true));
+
+        solver.backtrack_to_level(0);
+        assert_eq!(solver.history, lits(&[1, -9, 2]));
+    }
+
+    #[test]
+    fn pick_variable_reuses_its_scores() {
+        let mut solver = Solver::new_raw(random_3sat(20, 60, 7), 20);
+        let buffer = solver.scores.as_ptr();
+        loop {
+            // the most frequent unassigned variable over the unsatisfied
+            // clauses, counted afresh; the first one wins a tie
+            let mut counts = [0; 21];
+            for clause in &solver.clauses {
+                if clause.iter().any(|&lit| solver.val(lit) == Some(true)) {
+                    continue;
+                }
+                for &lit in clause {
+                    if solver.val(lit).is_none() {
+                        counts[lit.var() as usize] += 1;
+                    }
+                }
+            }
+            let best = counts.iter().copied().max().unwrap();
+            let first = counts.iter().position(|&c| c == best).unwrap();
+            let expected = if best == 0 { 0 } else { first };
+
+            let var = solver.pick_variable();
+            assert_eq!(var, expected);
+            assert_eq!(solver.scores.as_ptr(), buffer);
+            if var == 0 {
+                break;
+            }
+            solver.assignment.set(var, false);
+        }
+    }
+
+    #[test]
+    fn reduction_thins_learned_clauses() {
+        let reducing = |interval| SolverConfig {
+            reduce_interval: interval,
+            ..SolverConfig::default()
+        };
+        let (formula, num_vars) = pigeonhole(6);
+        let num_clauses = Solver::new(formula.clone(), num_vars).clauses.len();
+        let mut solver = Solver::with_config(formula.clone(), num_vars, reducing(100));
+        assert!(!solver.solve());
+        let stats = solver.stats();
+        assert!(stats.deleted > 0, "{:?}", stats);
+        assert_eq!(solver.clauses.len() as u64, num_clauses as u64 + stats.learned - stats.deleted);
+
+        let mut solver = Solver::with_config(formula, num_vars, reducing(0));
+        assert!(!solver.solve());
+        assert_eq!(solver.stats().deleted, 0);
+
+        for seed in 0..10 {
+            let formula = random_3sat(40, 170, seed);
+            let expected = Solver::with_config(formula.clone(), 40, reducing(0)).solve();
+            assert_eq!(Solver::with_config(formula, 40, reducing(10)).solve(), expected);
+        }
+    }
+
+    #[test]
+    fn interrupt_flag_stops_the_search() {
+        let (formula, num_vars) = pigeonhole(5);
+        let mut solver = Solver::new(formula, num_vars);
+        let flag = Arc::new(AtomicBool::new(true));
+        solver.set_interrupt(flag.clone());
+        assert_eq!(solver.solve_with_limit(u64::MAX), SolveResult::Unknown);
+        assert_eq!(solver.stats().conflicts, 1);
+        assert!(solver.history.is_empty());
+        assert_eq!(solver.model(), None);
+
+        flag.store(false, Ordering::Relaxed);
+        assert_eq!(solver.solve_with_limit(u64::MAX), SolveResult::Unsat);
+    }
+
+    #[test]
+    fn reset_keeps_verdicts() {
+        for seed in 0..10 {
+            let formula = random_3sat(40, 170, seed);
+            let expected = Solver::new(formula.clone(), 40).solve();
+            let mut solver = Solver::new(formula, 40);
+            assert_eq!(solver.solve(), expected, "seed {}", seed);
+            assert_eq!(solver.solve(), expected, "seed {}", seed);
+            solver.reset();
+            assert!(solver.history.is_empty());
+            assert!(solver.phases.iter().all(Option::is_none));
+            assert_eq!(solver.solve(), expected, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn learned_clauses_warm_start_later_solves() {
+        // refuting from scratch takes many conflicts; after a reset the
+        // retained clauses refute it again almost at once
+        let (formula, num_vars) = pigeonhole(5);
+        let mut solver = Solver::new(formula, num_vars);
+        assert!(!solver.solve());
+        let cold = solver.stats().conflicts;
+        let learned = solver.lbd.iter().filter(|&&lbd| lbd > 0).count();
+        assert!(learned > 0);
+        solver.reset();
+        assert_eq!(solver.lbd.iter().filter(|&&lbd| lbd > 0).count(), learned);
+        assert!(!solver.solve());
+        let warm = solver.stats().conflicts - cold;
+        assert!(warm * 4 < cold, "{} conflicts cold, {} warm", cold, warm);
+
+        // a satisfiable formula with a clause added in between
+        let formula = random_3sat(80, 330, 10);
+        let extra = lits(&[1, 2, 3]);
+        let mut fresh = Solver::new(formula.clone(), 80);
+        fresh.add_clause(extra.clone());
+        assert!(fresh.solve());
+        let mut solver = Solver::new(formula, 80);
+        assert!(solver.solve());
+        let before = solver.stats().conflicts;
+        solver.add_clause(extra);
+        solver.reset();
+        assert!(solver.solve());
+        let warm = solver.stats().conflicts - before;
+        assert!(warm < fresh.stats().conflicts, "{} warm, {:?} fresh", warm, fresh.stats());
+    }
+
+    #[test]
+    fn trivial_formulas_are_answered_without_search() {
+        let mut solver = Solver::new(Vec::new(), 3);
+        assert!(solver.solve());
+        assert_eq!(solver.model().unwrap()[1..], [true; 3]);
+        assert_eq!(solver.stats().decisions, 0);
+        assert_eq!(solver.stats().propagations, 0);
+
+        let mut solver = Solver::new(clauses(&[&[1], &[-1]]), 1);
+        assert!(!solver.solve());
+        assert_eq!(solver.stats().decisions, 0);
+    }
+
+    #[test]
+    fn trail_and_level_after_assumed_decisions() {
+        let config = SolverConfig {
+            pure_literals: false,
+            ..SolverConfig::default()
+        };
+        let formula = clauses(&[&[-1, 2], &[-3, 4], &[-2, -4, 1]]);
+        let mut solver = Solver::with_config(formula, 4, config);
+        assert_eq!(solver.decision_level(), 0);
+        assert!(solver.trail().is_empty());
+        // each assumption opens a level, and implies one more literal
+        assert_eq!(solver.solve_under_assumptions(&lits(&[1, 3])), SolveResult::Sat);
+        assert_eq!(solver.decision_level(), 2);
+        assert_eq!(solver.trail(), lits(&[1, 2, 3, 4]));
+        assert_eq!((1..=4).map(|v| solver.levels[v]).collect::<Vec<_>>(), [1, 1, 2, 2]);
+    }
+
+    #[test]
+    fn added_clauses_propagate() {
+        let mut solver = Solver::new(clauses(&[&[1, 2], &[-2, 3]]), 3);
+        assert!(solver.solve());
+        solver.add_clause(lits(&[-1, 4]));
+        solver.add_clause(lits(&[-4, -3, 5, 6]));
+        // a tautology is dropped
+        solver.add_clause(lits(&[2, -2]));
+        assert_eq!(solver.clauses.len(), solver.watches.len());
+        assert_eq!(solver.negatives[4], 1);
+
+        assert_eq!(solver.solve_under_assumptions(&lits(&[1, 3, -6])), SolveResult::Sat);
+        let model = solver.model().unwrap();
+        assert!(model[4] && model[5]);
+        assert_eq!(solver.solve_under_assumptions(&lits(&[1, 3, -5, -6])), SolveResult::Unsat);
+        // added clauses are part of the formula, so reset() keeps them
+        solver.reset();
+        assert_eq!(solver.solve_under_assumptions(&lits(&[1, -4])), SolveResult::Unsat);
+    }
+
+    #[test]
+    fn repeated_literals_are_dropped() {
+        let formula = clauses(&[&[1, 1, -2], &[2, 2], &[-1, -3, -1, -3], &[3, 1, 3, 4]]);
+        let mut solver = Solver::new(formula.clone(), 4);
+        assert!(solver.clauses.iter().all(|clause| {
+            (1..clause.len()).all(|i| !clause[..i].contains(&clause[i]))
+        }));
+        assert!(solver.solve());
+        assert!(check_model(&formula, &solver.model().unwrap()));
+
+        // added and temporary clauses too; (2 2) must still propagate as a unit
+        let mut solver = Solver::new(clauses(&[&[-1, -2]]), 2);
+        solver.add_clause(lits(&[1, 1, 1]));
+        assert_eq!(solver.clauses.last(), Some(&lits(&[1])));
+        assert!(!solver.with_temp_clause(&lits(&[2, 2]), |solver| solver.solve()));
+        assert!(solver.with_temp_clause(&lits(&[2, -2]), |solver| solver.solve()));
+    }
+
+    #[test]
+    fn propagate_units_lists_forced_literals() {
+        let formula = clauses(&[&[1], &[-1, 2], &[-2, -3], &[3, 4, 5], &[-4, 5, 6]]);
+        let mut solver = Solver::new(formula, 6);
+        let mut forced = solver.propagate_units().unwrap();
+        forced.sort_by_key(|lit| lit.var());
+        assert_eq!(forced, lits(&[1, 2, -3]));
+        assert!(solver.solve());
+        assert_eq!(solver.propagate_units().unwrap().len(), 3);
+
+        let mut solver = Solver::new(clauses(&[&[1], &[-1, 2], &[-2, 3], &[-3, -1]]), 3);
+        assert_eq!(solver.propagate_units(), None);
+        assert!(!solver.solve());
+    }
+
+    #[test]
+    fn simplify_drops_satisfied_clauses_and_false_literals() {
+        let formula = clauses(&[&[1, 2], &[-1, 3], &[2, 3, 4], &[-3, -4], &[2, 5, 6]]);
+        let mut solver = Solver::new_raw(formula.clone(), 6);
+        assert!(solver.solve());
+        solver.add_clause(lits(&[-2]));
+        solver.simplify();
+        // -2 forces 1, 3 and -4, which satisfy the first four clauses; the
+        // facts stay as units
+        let one = Lit::from(1);
+        assert!(!solver.clauses.iter().any(|clause| clause.len() > 1 && clause.contains(&one)));
+        assert!(solver.clauses.contains(&lits(&[5, 6])));
+        assert!(!solver.clauses.contains(&lits(&[2, 5, 6])));
+        for fact in [-2, 1, 3, -4] {
+            assert!(solver.clauses.contains(&lits(&[fact])), "{}", fact);
+        }
+
+        assert!(solver.solve());
+        let model = solver.model().unwrap();
+        assert!(check_model(&formula, &model));
+        assert!(!model[2]);
+        solver.add_clause(lits(&[-5]));
+        solver.add_clause(lits(&[-6]));
+        solver.simplify();
+        assert!(!solver.solve());
+    }
+
+    #[test]
+    fn lbd_counts_decision_levels() {
+        let mut solver = Solver::new(clauses(&[&[1, 2, 3, 4]]), 4);
+        solver.levels[1..=4].copy_from_slice(&[3, 3, 3, 1]);
+        assert_eq!(solver.compute_lbd(&lits(&[1, -2, 3])), 1);
+        assert_eq!(solver.compute_lbd(&lits(&[-1, 4])), 2);
+
+        let (formula, num_vars) = pigeonhole(5);
+        for restarts in [Restarts::Lbd, Restarts::Luby] {
+            let config = SolverConfig {
+                restarts,
+                ..SolverConfig::default()
+            };
+            let mut solver = Solver::with_config(formula.clone(), num_vars, config);
+            assert!(!solver.solve());
+            let stats = solver.stats();
+            let learned_lbds: u64 = solver.lbd.iter().map(|&lbd| u64::from(lbd)).sum();
+            assert!(stats.average_lbd() >= 1.0, "{:?}", stats);
+            assert!(stats.lbd_total >= learned_lbds, "{:?}", stats);
+        }
+    }
+
+    #[test]
+    fn backbone_of_one_variable() {
+        // 1 holds in every model, while 2, 3 and 4 take both values
+        let formula = clauses(&[&[1, 2], &[1, -2], &[3, 4], &[-3, -4, 2]]);
+        for config in [SolverConfig::default(), eliminating()] {
+            let mut solver = Solver::with_config(formula.clone(), 4, config);
+            assert_eq!(solver.backbone(), lits(&[1]));
+            assert!(solver.solve());
+        }
+        assert!(Solver::new(clauses(&[&[1], &[-1]]), 1).backbone().is_empty());
+    }
+
+    #[test]
+    fn full_phase_hint_solves_without_conflicts() {
+        // random 3-clauses all satisfied by the planted model x(v) = v is odd
+        let planted = |var: Var| var % 2 == 1;
+        let formula: Vec<Clause> = random_3sat(50, 400, 9)
+            .into_iter()
+            .filter(|clause| clause.iter().any(|lit| planted(lit.var()) == lit.is_positive()))
+            .collect();
+        assert!(formula.len() > 300);
+
+        let mut solver = Solver::new(formula, 50);
+        let hints: Vec<(Var, bool)> = (1..=50).map(|var| (var, planted(var))).collect();
+        solver.set_phase_hints(&hints);
+        // out of range, ignored
+        solver.set_phase_hints(&[(0, true), (51, true)]);
+        assert!(solver.solve());
+        assert_eq!(solver.stats().conflicts, 0);
+    }
+
+    #[test]
+    fn unit_clauses_watch_in_bounds() {
+        let mut solver = Solver::new(clauses(&[&[-1, 2, 3], &[1], &[-2, -3], &[-3, 4]]), 4);
+        solver.add_clause(lits(&[-4]));
+        for (clause, &(first, second)) in solver.clauses.iter().zip(&solver.watches) {
+            assert!(first < clause.len() && second < clause.len(), "{:?}", clause);
+        }
+        // 1 and -4 force 2 and -3 through the longer clauses
+        assert!(solver.solve());
+        assert_eq!(solver.model().unwrap()[1..], [true, true, false, false]);
+        assert!(!solver.with_temp_clause(&lits(&[-2]), |solver| solver.solve()));
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::ops::Neg;
+
+/// Integer a literal is stored as: `i32` by default, `i64` with the `i64`
+/// feature, which doubles the size of every clause.
+#[cfg(not(feature = "i64"))]
+pub type LitInt = i32;
+#[cfg(feature = "i64")]
+pub type LitInt = i64;
+
+/// Variable index, the unsigned counterpart of `LitInt`.
+#[cfg(not(feature = "i64"))]
+pub type Var = u32;
+#[cfg(feature = "i64")]
+pub type Var = u64;
+
+/// A literal: variable `v` or its negation, kept in the DIMACS encoding `v` /
+/// `-v` so it converts to and from the integers in a CNF file for free.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lit(LitInt);
+
+impl Lit {
+    /// The literal of `var` that is true when `var` has the value `positive`.
+    #[inline]
+    pub fn new(var: Var, positive: bool) -> Lit {
+        let lit = var as LitInt;
+        Lit(if positive { lit } else { -lit })
+    }
+
+    #[inline]
+    pub fn var(self) -> Var {
+        self.0.unsigned_abs()
+    }
+
+    #[inline]
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    #[inline]
+    pub fn negate(self) -> Lit {
+        Lit(-self.0)
+    }
+
+    /// Slot of this literal in per-literal tables such as the watch lists:
+    /// `2v` for `v` and `2v + 1` for `-v`. With the `i64` feature this needs
+    /// `v < usize::MAX / 2`, which holds for any formula whose tables fit in
+    /// memory; debug builds check it.
+    #[inline]
+    pub fn index(self) -> usize {
+        debug_assert!(usize::try_from(self.var()).is_ok_and(|var| var < usize::MAX / 2));
+        2 * self.var() as usize + usize::from(!self.is_positive())
+    }
+}
+
+impl From<i32> for Lit {
+    #[inline]
+    fn from(lit: i32) -> Lit {
+        Lit(LitInt::from(lit))
+    }
+}
+
+#[cfg(feature = "i64")]
+impl From<i64> for Lit {
+    #[inline]
+    fn from(lit: i64) -> Lit {
+        Lit(lit)
+    }
+}
+
+impl From<Lit> for LitInt {
+    #[inline]
+    fn from(lit: Lit) -> LitInt {
+        lit.0
+    }
+}
+
+impl Neg for Lit {
+    type Output = Lit;
+
+    #[inline]
+    fn neg(self) -> Lit {
+        self.negate()
+    }
+}
+
+impl fmt::Display for Lit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_round_trips() {
+        let mut seen = Vec::new();
+        for var in 1..=50 as Var {
+            for positive in [true, false] {
+                let lit = Lit::new(var, positive);
+                assert_eq!((lit.var(), lit.is_positive()), (var, positive));
+                assert_eq!(Lit::from(LitInt::from(lit)), lit);
+                assert_eq!(lit.negate().index() ^ 1, lit.index());
+                assert_eq!(lit.index() / 2, var as usize);
+                seen.push(lit.index());
+            }
+        }
+        // every slot from 2 on is used exactly once
+        seen.sort();
+        assert_eq!(seen, (2..102).collect::<Vec<_>>());
+        assert_eq!(-Lit::from(-7), Lit::from(7));
+        assert_eq!(Lit::from(-7).to_string(), "-7");
+    }
+
+    #[cfg(feature = "i64")]
+    #[test]
+    fn variables_beyond_i32_round_trip() {
+        let var = i32::MAX as Var + 5;
+        for positive in [true, false] {
+            let lit = Lit::new(var, positive);
+            assert_eq!(lit.var(), var);
+            let int = LitInt::from(lit);
+            assert_eq!(int.unsigned_abs(), var);
+            assert_eq!(Lit::from(int), lit);
+            assert_eq!(lit.index(), 2 * var as usize + usize::from(!positive));
+            assert_eq!(lit.negate().index() ^ 1, lit.index());
+        }
+    }
+}
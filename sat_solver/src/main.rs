@@ -1,404 +1,1458 @@
-use std::io::{self, Read};
-
-type Lit = i32;
-type Clause = Vec<Lit>;
-
-struct Solver {
-    clauses: Vec<Clause>,
-    num_vars: usize,
-    assignment: Vec<Option<bool>>,
-    history: Vec<Lit>,
-    positives: Vec<usize>,
-    negatives: Vec<usize>,
-    literal_polarity: Vec<i8>,
-    watches: Vec<(usize, usize)>,
-    watch_lists: Vec<Vec<usize>>,
-}
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-impl Solver {
-    fn new(clauses: Vec<Clause>, num_vars: usize) -> Self {
-        let mut solver = Solver {
-            clauses,
-            num_vars,
-            assignment: vec![None; num_vars + 1],
-            history: Vec::with_capacity(num_vars + 1),
-            positives: vec![0; num_vars + 1],
-            negatives: vec![0; num_vars + 1],
-            literal_polarity: vec![0; num_vars + 1],
-            watches: Vec::new(),
-            watch_lists: vec![Vec::new(); 2 * (num_vars + 1)],
-        };
-        solver.preprocess();
-        solver
+use sat_solver::{
+    gunzip, is_gzip, parse_assumptions, Clause, Dimacs, Lit, LitInt, ParseError, Progress,
+    SolveResult, Solver, Stats,
+};
+
+// Ctrl-C sets a flag the solver polls instead of killing the process, so an
+// interrupted run still reports "s UNKNOWN" and its stats. signal() comes from
+// the C library std already links against.
+#[cfg(unix)]
+mod sigint {
+    use std::ffi::c_int;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, OnceLock};
+
+    const SIGINT: c_int = 2;
+
+    static FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+    unsafe extern "C" {
+        fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
     }
 
-    fn lit_index(&self, lit: Lit) -> usize {
-        if lit > 0 {
-            (lit as usize) * 2
-        } else {
-            ((-lit) as usize) * 2 + 1
+    extern "C" fn on_sigint(_: c_int) {
+        if let Some(flag) = FLAG.get() {
+            flag.store(true, Ordering::Relaxed);
         }
     }
 
-    fn preprocess(&mut self) {
-        self.clauses.retain(|clause| {
-            for i in 0..clause.len() {
-                for j in (i + 1)..clause.len() {
-                    if clause[i] == -clause[j] {
-                        return false;
-                    }
-                }
-            }
-            true
-        });
-        
-        // So we are checking shortest clauses first, exploring variables that affect the first clauses. Optimization
-        self.clauses.sort_by_key(|c| c.len());
-
-        
-        self.watches = vec![(0, 1); self.clauses.len()];
-        // two-watched literals
-        for (idx, clause) in self.clauses.iter().enumerate() {
-            if clause.is_empty() {
-                continue;
+    pub fn install(flag: Arc<AtomicBool>) {
+        if FLAG.set(flag).is_ok() {
+            // SAFETY: the handler only does an atomic store
+            unsafe {
+                signal(SIGINT, on_sigint);
             }
+        }
+    }
+}
 
-            let lit_idx = self.lit_index(clause[0]);
-            self.watch_lists[lit_idx].push(idx);
+// Reads the whole input from `path`, or from stdin when no path (or "-") is given.
+// A .gz file, or any input starting with the gzip magic bytes, is decompressed.
+fn read_input(path: Option<&str>) -> io::Result<String> {
+    let mut buffer = Vec::new();
+    match path {
+        None | Some("-") => {
+            io::stdin().lock().read_to_end(&mut buffer)?;
+        }
+        Some(path) => {
+            File::open(path)?.read_to_end(&mut buffer)?;
+        }
+    }
+    if path.is_some_and(|p| p.ends_with(".gz")) || is_gzip(&buffer) {
+        buffer = gunzip(&buffer)?;
+    }
+    String::from_utf8(buffer).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+const USAGE: &str = "usage: sat_solver [-v | -vv] [--stats] [--json] [--report] [--strict] \
+                     [--verify] [--proof PROOF] [--model-out MODEL] \
+                     [--assume LIT | ASSUMPTIONS]... [--dump-conflict DOT] \
+                     [--model-width COLUMNS] [--check-model MODEL] [FILE... | DIR | -]";
+
+// Everything that makes a run fail, printed by main() before exiting with 1.
+// Panics are left for bugs in the solver itself.
+#[derive(Debug)]
+enum AppError {
+    Usage,
+    Io { context: String, source: io::Error },
+    Parse(ParseError),
+    // input or options the solver can't handle together
+    Unsupported(String),
+    // a model that --verify found to falsify the input
+    Verify(String),
+}
 
-            if clause.len() > 1 {
-                let lit_idx2 = self.lit_index(clause[1]);
-                self.watch_lists[lit_idx2].push(idx);
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Usage => write!(f, "{}", USAGE),
+            AppError::Io { context, source } => write!(f, "error: {}: {}", context, source),
+            AppError::Parse(e) => write!(f, "error: {}", e),
+            AppError::Unsupported(message) | AppError::Verify(message) => {
+                write!(f, "error: {}", message)
             }
         }
+    }
+}
+
+impl From<ParseError> for AppError {
+    fn from(e: ParseError) -> Self {
+        AppError::Parse(e)
+    }
+}
 
+// Wraps an IO error with what was being done, e.g. "cannot read FILE"
+fn io_error(context: String) -> impl FnOnce(io::Error) -> AppError {
+    move |source| AppError::Io { context, source }
+}
 
-        // Pure literal optimization 
-        for clause in &self.clauses {
-            for &lit in clause {
-                let var = lit.abs() as usize;
-                if lit > 0 {
-                    self.positives[var] += 1;
-                } else {
-                    self.negatives[var] += 1;
-                }
+// Width the "v" lines of a model are wrapped at unless --model-width says
+// otherwise; some checkers reject longer lines.
+const MODEL_WIDTH: usize = 80;
+
+// Writes the model as DIMACS "v" lines of at most `width` characters, each
+// starting with "v", and terminates the last one with 0. A literal too long
+// for the width on its own still gets a line of its own.
+fn write_model(out: &mut impl Write, model: &[bool], width: usize) -> io::Result<()> {
+    let mut line = String::from("v");
+    for (i, &value) in model.iter().enumerate().skip(1) {
+        let lit = if value { format!(" {}", i) } else { format!(" -{}", i) };
+        if line.len() > 1 && line.len() + lit.len() > width {
+            writeln!(out, "{}", line)?;
+            line = String::from("v");
+        }
+        line.push_str(&lit);
+    }
+    if line.len() > 1 && line.len() + 2 > width {
+        writeln!(out, "{}", line)?;
+        line = String::from("v");
+    }
+    writeln!(out, "{} 0", line)
+}
+
+// Writes the "s" line of a solve, followed by the model for SAT, and
+// preceded by the failed assumptions for UNSAT under --assume.
+fn write_outcome(
+    out: &mut impl Write,
+    result: SolveResult,
+    model: Option<&[bool]>,
+    core: Option<&[Lit]>,
+    width: usize,
+) -> io::Result<()> {
+    match result {
+        SolveResult::Sat => {
+            writeln!(out, "s SATISFIABLE")?;
+            write_model(out, model.expect("a SAT answer without a model"), width)
+        }
+        SolveResult::Unsat => {
+            if let Some(core) = core {
+                write_core(out, core)?;
             }
+            writeln!(out, "s UNSATISFIABLE")
         }
+        // only an interrupt stops an unlimited solve early
+        SolveResult::Unknown => writeln!(out, "s UNKNOWN"),
+    }
+}
+
+// Writes the model for --model-out, one "VAR true" or "VAR false" line per
+// variable.
+fn write_model_table(out: &mut impl Write, model: &[bool]) -> io::Result<()> {
+    for (var, value) in model.iter().enumerate().skip(1) {
+        writeln!(out, "{} {}", var, value)?;
+    }
+    Ok(())
+}
+
+// Creates `path` and writes the --model-out table to it.
+fn save_model_table(path: &str, model: &[bool]) -> Result<(), AppError> {
+    let file = File::create(path).map_err(io_error(format!("cannot create {}", path)))?;
+    let mut out = BufWriter::new(file);
+    write_model_table(&mut out, model)
+        .and_then(|()| out.flush())
+        .map_err(io_error(format!("cannot write {}", path)))
+}
+
+// Writes the failed assumptions of an --assume run as a "c core ... 0" line.
+fn write_core(out: &mut impl Write, core: &[Lit]) -> io::Result<()> {
+    write!(out, "c core")?;
+    for lit in core {
+        write!(out, " {}", lit)?;
+    }
+    writeln!(out, " 0")
+}
+
+// Writes the search counters as comment lines.
+fn write_stats(out: &mut impl Write, stats: &Stats) -> io::Result<()> {
+    writeln!(out, "c decisions    {}", stats.decisions)?;
+    writeln!(out, "c propagations {}", stats.propagations)?;
+    writeln!(out, "c conflicts    {}", stats.conflicts)?;
+    writeln!(out, "c restarts     {}", stats.restarts)?;
+    writeln!(out, "c learned      {}", stats.learned)?;
+    writeln!(out, "c deleted      {}", stats.deleted)?;
+    writeln!(out, "c average lbd  {:.2}", stats.average_lbd())?;
+    writeln!(out, "c agility      {:.2}", stats.agility)
+}
+
+// Time between the progress lines of -v.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+// Writes a -v progress line, with the current decision under -vv.
+fn write_progress(
+    out: &mut impl Write,
+    progress: &Progress,
+    elapsed: Duration,
+    verbosity: u8,
+) -> io::Result<()> {
+    let stats = progress.stats;
+    write!(
+        out,
+        "c progress {:.1}s: {} conflicts, {} restarts, {} learned clauses",
+        elapsed.as_secs_f64(),
+        stats.conflicts,
+        stats.restarts,
+        progress.learned_clauses
+    )?;
+    if let (2.., Some(lit)) = (verbosity, progress.decision) {
+        write!(out, ", deciding {}", lit)?;
+    }
+    writeln!(out)
+}
+
+// Writes the --report summary, one row per figure with the values lined up
+// in a column. `size` is the formula's number of variables and clauses;
+// search counters are left out when there are none, as for MaxSAT.
+fn write_report(
+    out: &mut impl Write,
+    status: &str,
+    size: (usize, usize),
+    stats: Option<&Stats>,
+    elapsed: Duration,
+) -> io::Result<()> {
+    let (num_vars, num_clauses) = size;
+    let mut rows = vec![
+        ("status", status.to_string()),
+        ("variables", num_vars.to_string()),
+        ("clauses", num_clauses.to_string()),
+    ];
+    if let Some(stats) = stats {
+        rows.push(("decisions", stats.decisions.to_string()));
+        rows.push(("conflicts", stats.conflicts.to_string()));
+        rows.push(("propagations", stats.propagations.to_string()));
+        rows.push(("restarts", stats.restarts.to_string()));
+    }
+    rows.push(("time", format!("{:.3}s", elapsed.as_secs_f64())));
+
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    for (label, value) in rows {
+        writeln!(out, "{:<width$}  {}", label, value)?;
+    }
+    Ok(())
+}
+
+// Writes the outcome as one JSON object for --json: the status ("sat",
+// "unsat" or "unknown"), the model as signed literals when there is one, the
+// MaxSAT cost, the failed assumptions of an --assume run, and the search
+// counters.
+fn write_json(
+    out: &mut impl Write,
+    status: &str,
+    model: Option<&[bool]>,
+    cost: Option<u64>,
+    core: Option<&[Lit]>,
+    stats: Option<&Stats>,
+) -> io::Result<()> {
+    let mut json = format!("{{\"status\":\"{}\"", status);
+    if let Some(cost) = cost {
+        json.push_str(&format!(",\"cost\":{}", cost));
+    }
+    if let Some(core) = core {
+        let lits: Vec<String> = core.iter().map(|lit| lit.to_string()).collect();
+        json.push_str(&format!(",\"core\":[{}]", lits.join(",")));
+    }
+    if let Some(model) = model {
+        let lits: Vec<String> = model
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, &value)| if value { format!("{}", i) } else { format!("-{}", i) })
+            .collect();
+        json.push_str(&format!(",\"model\":[{}]", lits.join(",")));
+    }
+    if let Some(stats) = stats {
+        let counters = [
+            ("decisions", stats.decisions),
+            ("propagations", stats.propagations),
+            ("conflicts", stats.conflicts),
+            ("restarts", stats.restarts),
+            ("learned", stats.learned),
+            ("deleted", stats.deleted),
+        ];
+        let fields: Vec<String> = counters
+            .iter()
+            .map(|(name, value)| format!("\"{}\":{}", name, value))
+            .collect();
+        json.push_str(&format!(
+            ",\"stats\":{{{},\"average_lbd\":{:.2},\"agility\":{:.2}}}",
+            fields.join(","),
+            stats.average_lbd(),
+            stats.agility
+        ));
+    }
+    json.push('}');
+    writeln!(out, "{}", json)
+}
 
-        for v in 1..=self.num_vars {
-            if self.positives[v] > 0 && self.negatives[v] == 0 {
-                self.literal_polarity[v] = 1;
-            } else if self.negatives[v] > 0 && self.positives[v] == 0 {
-                self.literal_polarity[v] = -1;
+// Describes the first of the clauses, and then of the XOR constraints, that
+// `model` falsifies, with its position in the input counting from 1; None if
+// it satisfies them all.
+fn falsified(clauses: &[Clause], xors: &[Clause], model: &[bool]) -> Option<String> {
+    let holds = |lit: &Lit| model.get(lit.var() as usize) == Some(&lit.is_positive());
+    let show = |lits: &Clause| {
+        let lits: Vec<String> = lits.iter().map(|lit| lit.to_string()).collect();
+        lits.join(" ")
+    };
+    if let Some((i, clause)) = clauses.iter().enumerate().find(|(_, c)| !c.iter().any(holds)) {
+        return Some(format!("clause {}: {} 0", i + 1, show(clause)));
+    }
+    let odd = |xor: &Clause| xor.iter().filter(|lit| holds(lit)).count() % 2 == 1;
+    if let Some((i, xor)) = xors.iter().enumerate().find(|(_, xor)| !odd(xor)) {
+        return Some(format!("XOR constraint {}: x {} 0", i + 1, show(xor)));
+    }
+    None
+}
+
+// Re-checks `model` for --verify against the clauses and XOR constraints as
+// they were read, before preprocessing touched them, failing with the first
+// one it violates.
+fn verify_model(clauses: &[Clause], xors: &[Clause], model: &[bool]) -> Result<(), AppError> {
+    match falsified(clauses, xors, model) {
+        Some(what) => Err(AppError::Verify(format!("model falsifies {}", what))),
+        None => Ok(()),
+    }
+}
+
+// Reads the assignment of --check-model from `input`: signed literals ended
+// by a 0, for variables up to `num_vars`. A line may start with "v", and
+// "s" lines are skipped, so the solver's own output can be checked as is.
+fn parse_assignment(input: &str, num_vars: usize) -> Result<Vec<Option<bool>>, AppError> {
+    let starts_with_word = |line: &str, word: char| {
+        line.strip_prefix(word)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+    };
+    // blank lines instead of the dropped ones keep the line numbers of errors
+    let lits: Vec<&str> = input
+        .lines()
+        .map(|line| {
+            let line = line.trim_start();
+            if starts_with_word(line, 's') {
+                ""
+            } else if starts_with_word(line, 'v') {
+                &line[1..]
+            } else {
+                line
             }
+        })
+        .collect();
+
+    let mut assigned = vec![None; num_vars + 1];
+    for lit in parse_assumptions(&lits.join("\n"))? {
+        let var = lit.var() as usize;
+        if var > num_vars {
+            return Err(AppError::Unsupported(format!(
+                "assignment sets {}, a variable the formula doesn't have",
+                lit
+            )));
         }
+        if assigned[var].replace(lit.is_positive()) == Some(!lit.is_positive()) {
+            return Err(AppError::Unsupported(format!(
+                "assignment sets variable {} both ways",
+                var
+            )));
+        }
+    }
+    Ok(assigned)
+}
+
+// Describes the first clause or XOR constraint that `assigned` falsifies, as
+// falsified() does; None if it satisfies them all. A variable it leaves unset
+// is an error, unless every clause with it is satisfied anyway.
+fn check_partial(
+    clauses: &[Clause],
+    xors: &[Clause],
+    assigned: &[Option<bool>],
+) -> Result<Option<String>, AppError> {
+    let holds = |lit: &Lit| assigned[lit.var() as usize] == Some(lit.is_positive());
+    let unsatisfied = clauses.iter().filter(|clause| !clause.iter().any(holds));
+    if let Some(lit) = unsatisfied
+        .chain(xors)
+        .flatten()
+        .find(|lit| assigned[lit.var() as usize].is_none())
+    {
+        return Err(AppError::Unsupported(format!(
+            "assignment leaves variable {} unset",
+            lit.var()
+        )));
+    }
+    // the unset variables only occur in satisfied clauses now
+    let model: Vec<bool> = assigned.iter().map(|value| value.unwrap_or(false)).collect();
+    Ok(falsified(clauses, xors, &model))
+}
 
+// Checks the assignment in the file at `path` for --check-model against the
+// formula instead of solving it, as read by parse_assignment(). Reports
+// SATISFIABLE if it satisfies every clause and XOR constraint, and otherwise
+// UNSATISFIABLE with the first one it falsifies, exiting with 10 or 20 as for
+// a solve.
+fn check_assignment(
+    path: &str,
+    dimacs: &Dimacs,
+    options: &Options,
+    start: Instant,
+) -> Result<i32, AppError> {
+    if dimacs.top.is_some() {
+        return Err(AppError::Unsupported(
+            "--check-model is not supported for WCNF input".into(),
+        ));
     }
-    
-    #[inline(always)]
-    fn val(&self, lit: Lit) -> Option<bool> {
-        let var_idx = lit.abs() as usize;
-        match self.assignment[var_idx] {
-            Some(val) => {
-                if lit > 0 { 
-                    Some(val) 
-                } else { 
-                    Some(!val) 
+    if options.proof_path.is_some()
+        || options.assumes()
+        || options.dump_path.is_some()
+        || options.model_path.is_some()
+        || options.verify
+        || options.show_stats
+        || options.verbosity > 0
+    {
+        return Err(AppError::Unsupported(
+            "--check-model doesn't solve, so it can't be combined with -v, --stats, --verify, \
+             --proof, --assume, --dump-conflict or --model-out"
+                .into(),
+        ));
+    }
+
+    let input = read_input(Some(path)).map_err(io_error(format!("cannot read {}", path)))?;
+    let assigned = parse_assignment(&input, dimacs.num_vars)?;
+    let failure = check_partial(&dimacs.clauses, &dimacs.xors, &assigned)?;
+    let (status, code) = match failure {
+        Some(_) => ("UNSATISFIABLE", 20),
+        None => ("SATISFIABLE", 10),
+    };
+    let note = failure.map(|what| format!("c assignment falsifies {}", what));
+    if options.json {
+        if let Some(note) = note {
+            eprintln!("{}", note);
+        }
+        let status = if code == 10 { "sat" } else { "unsat" };
+        write_json(&mut io::stdout().lock(), status, None, None, None, None)
+            .map_err(io_error("cannot write output".into()))?;
+    } else {
+        if let Some(note) = note {
+            println!("{}", note);
+        }
+        println!("s {}", status);
+    }
+    if options.report {
+        let size = (dimacs.num_vars, dimacs.clause_count());
+        write_report(&mut io::stderr().lock(), status, size, None, start.elapsed())
+            .map_err(io_error("cannot write report".into()))?;
+    }
+    Ok(code)
+}
+
+// Command-line options.
+#[derive(Default)]
+struct Options {
+    paths: Vec<String>,
+    proof_path: Option<String>,
+    model_path: Option<String>,
+    assumptions_path: Option<String>,
+    // literals given to --assume one at a time
+    assumed: Vec<Lit>,
+    dump_path: Option<String>,
+    check_path: Option<String>,
+    // 0 by default, 1 for -v and 2 for -vv
+    verbosity: u8,
+    model_width: usize,
+    show_stats: bool,
+    json: bool,
+    report: bool,
+    strict: bool,
+    verify: bool,
+}
+
+impl Options {
+    fn parse(args: Vec<String>) -> Result<Options, AppError> {
+        let mut options = Options {
+            model_width: MODEL_WIDTH,
+            ..Options::default()
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-v" => options.verbosity = options.verbosity.max(1),
+                "-vv" => options.verbosity = 2,
+                "--stats" => options.show_stats = true,
+                "--json" => options.json = true,
+                "--report" => options.report = true,
+                "--strict" => options.strict = true,
+                "--verify" => options.verify = true,
+                "--proof" => options.proof_path = Some(args.next().ok_or(AppError::Usage)?),
+                "--model-out" => options.model_path = Some(args.next().ok_or(AppError::Usage)?),
+                // a literal of its own, or a file listing some
+                "--assume" => {
+                    let arg = args.next().ok_or(AppError::Usage)?;
+                    match arg.parse::<LitInt>() {
+                        Ok(0 | LitInt::MIN) => return Err(AppError::Usage),
+                        Ok(lit) => options.assumed.push(Lit::from(lit)),
+                        Err(_) => options.assumptions_path = Some(arg),
+                    }
                 }
-            }
-            None => None,
-        }
-    }
-    
-    #[warn(dead_code)]
-    fn pick_variable_two(&self) -> usize {
-        let mut best_var = 0;
-        let mut best_score = 0;
-        
-        for v in 1..=self.num_vars {
-            if self.assignment[v].is_none() {
-                let score = self.positives[v] + self.negatives[v];
-                if score > best_score {
-                    best_score = score;
-                    best_var = v;
+                "--dump-conflict" => {
+                    options.dump_path = Some(args.next().ok_or(AppError::Usage)?)
                 }
-            }
-        }
-        
-        best_var
-    }
-    
-    fn pick_variable(&self) -> usize {
-        // heuristic picking variable appearing in most unresolved clauses
-        let mut scores = vec![0; self.num_vars + 1];
-        for clause in &self.clauses {
-            let mut satisfied = false;
-            for &lit in clause {
-                if let Some(true) = self.val(lit) {
-                    satisfied = true;
-                    break;
+                "--check-model" => {
+                    options.check_path = Some(args.next().ok_or(AppError::Usage)?)
                 }
-            }
-            if !satisfied {
-                for &lit in clause {
-                    let var = lit.abs() as usize;
-                    if self.assignment[var].is_none() {
-                        scores[var] += 1;
-                    }
+                "--model-width" => {
+                    let width = args.next().ok_or(AppError::Usage)?;
+                    options.model_width = width.parse().map_err(|_| AppError::Usage)?;
                 }
+                // "-" is stdin; anything else with a dash is an unknown option
+                _ if arg.starts_with('-') && arg != "-" => return Err(AppError::Usage),
+                _ => options.paths.push(arg),
             }
         }
-        
+        Ok(options)
+    }
 
-        let mut best_var = 0;
-        let mut best_score = 0;
+    // Whether --assume was given, by literal or by file.
+    fn assumes(&self) -> bool {
+        self.assumptions_path.is_some() || !self.assumed.is_empty()
+    }
+}
 
-        for v in 1..=self.num_vars {
-            if self.assignment[v].is_none() && scores[v] > best_score {
-                best_score = scores[v];
-                best_var = v;
-            }
+fn read_dimacs(path: Option<&str>, strict: bool) -> Result<Dimacs, AppError> {
+    let buffer =
+        read_input(path).map_err(io_error(format!("cannot read {}", path.unwrap_or("stdin"))))?;
+    if strict {
+        Ok(Dimacs::parse_strict(&buffer)?)
+    } else {
+        Ok(Dimacs::parse(&buffer)?)
+    }
+}
+
+// Outcome of one instance of a batch run.
+enum Verdict {
+    Sat,
+    Unsat,
+    Optimum(u64),
+    Unknown,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Sat => write!(f, "SAT"),
+            Verdict::Unsat => write!(f, "UNSAT"),
+            Verdict::Optimum(cost) => write!(f, "OPTIMUM {}", cost),
+            Verdict::Unknown => write!(f, "UNKNOWN"),
+        }
+    }
+}
+
+// Solves the instance in `path` with a fresh solver for a batch run.
+fn solve_instance(
+    path: &str,
+    options: &Options,
+    interrupt: &Arc<AtomicBool>,
+) -> Result<Verdict, AppError> {
+    let dimacs = read_dimacs(Some(path), options.strict)?;
+    if dimacs.top.is_some() {
+        if !dimacs.xors.is_empty() {
+            return Err(AppError::Unsupported(
+                "XOR constraints are not supported for WCNF input".into(),
+            ));
         }
+        return match Solver::solve_maxsat(&dimacs.clauses, &dimacs.soft, dimacs.num_vars) {
+            Some((cost, model)) => {
+                if options.verify {
+                    verify_model(&dimacs.clauses, &[], &model)?;
+                }
+                Ok(Verdict::Optimum(cost))
+            }
+            None => Ok(Verdict::Unsat),
+        };
+    }
 
-        best_var
+    let original = options.verify.then(|| dimacs.clauses.clone());
+    let mut solver = Solver::new(dimacs.clauses, dimacs.num_vars);
+    for xor in &dimacs.xors {
+        solver.add_xor(xor);
     }
+    solver.set_interrupt(interrupt.clone());
+    let result = solver.solve_with_limit(u64::MAX);
+    if let (Some(clauses), Some(model)) = (&original, solver.model()) {
+        verify_model(clauses, &dimacs.xors, &model)?;
+    }
+    Ok(match result {
+        SolveResult::Sat => Verdict::Sat,
+        SolveResult::Unsat => Verdict::Unsat,
+        SolveResult::Unknown => Verdict::Unknown,
+    })
+}
 
+// Solves every file named on the command line, and every file in a named
+// directory, one after another, printing "FILE: VERDICT" for each and a
+// summary line at the end. An instance that fails is reported in its line
+// and makes the exit code 1. Ctrl-C stops the batch after the current
+// instance, which then counts as unknown.
+fn run_batch(options: &Options, out: &mut impl Write) -> Result<i32, AppError> {
+    if options.json
+        || options.show_stats
+        || options.report
+        || options.proof_path.is_some()
+        || options.model_path.is_some()
+        || options.assumes()
+        || options.dump_path.is_some()
+        || options.check_path.is_some()
+        || options.verbosity > 0
+    {
+        return Err(AppError::Unsupported(
+            "-v, --json, --stats, --report, --proof, --model-out, --assume, --dump-conflict and \
+             --check-model take a single instance"
+                .into(),
+        ));
+    }
+    let mut paths = Vec::new();
+    for path in &options.paths {
+        if !Path::new(path).is_dir() {
+            paths.push(path.clone());
+            continue;
+        }
+        let mut files = Vec::new();
+        for entry in fs::read_dir(path).map_err(io_error(format!("cannot read {}", path)))? {
+            let entry = entry.map_err(io_error(format!("cannot read {}", path)))?;
+            if entry.path().is_file() {
+                files.push(entry.path().to_string_lossy().into_owned());
+            }
+        }
+        files.sort();
+        paths.extend(files);
+    }
 
+    let interrupt = Arc::new(AtomicBool::new(false));
+    #[cfg(unix)]
+    sigint::install(interrupt.clone());
 
-    fn solve(&mut self) -> bool {
-        // Unit propagation
-        let entry_snapshot = self.history.len();
-        
-        if entry_snapshot == 0 {
-            for v in 1..=self.num_vars {
-                if self.assignment[v].is_none() && self.literal_polarity[v] != 0 {
-                    let lit = if self.literal_polarity[v] > 0 {
-                        v as i32
-                    } else {
-                        -(v as i32)
-                    };
-                    if !self.propagate(lit) {
-                        self.backtrack(entry_snapshot);
-                        return false;
-                    }
-                    self.assign_lit(lit);
+    let start = Instant::now();
+    let (mut sat, mut unsat, mut optimum, mut unknown, mut errors) = (0, 0, 0, 0, 0);
+    for path in &paths {
+        match solve_instance(path, options, &interrupt) {
+            Ok(verdict) => {
+                writeln!(out, "{}: {}", path, verdict)
+                    .map_err(io_error("cannot write output".into()))?;
+                match verdict {
+                    Verdict::Sat => sat += 1,
+                    Verdict::Unsat => unsat += 1,
+                    Verdict::Optimum(_) => optimum += 1,
+                    Verdict::Unknown => unknown += 1,
                 }
             }
+            Err(e) => {
+                writeln!(out, "{}: {}", path, e).map_err(io_error("cannot write output".into()))?;
+                errors += 1;
+            }
         }
-        
-        if !self.bcp() {
-            self.backtrack(entry_snapshot);
-            return false;
+        if interrupt.load(Ordering::Relaxed) {
+            break;
         }
+    }
+    writeln!(
+        out,
+        "c {} instances: {} SAT, {} UNSAT, {} OPTIMUM, {} UNKNOWN, {} errors in {:.2}s",
+        sat + unsat + optimum + unknown + errors,
+        sat,
+        unsat,
+        optimum,
+        unknown,
+        errors,
+        start.elapsed().as_secs_f64()
+    )
+    .map_err(io_error("cannot write output".into()))?;
+    Ok(if errors > 0 { 1 } else { 0 })
+}
 
-        let pick_var = self.pick_variable();
+// Runs the solver as the command line `args` (without the program name)
+// asks and returns the exit code: 10 for SAT, 20 for UNSAT, 30 for a MaxSAT
+// optimum and 0 if the search was interrupted.
+fn run(args: Vec<String>) -> Result<i32, AppError> {
+    let options = Options::parse(args)?;
+    if options.paths.len() > 1 || options.paths.first().is_some_and(|p| Path::new(p).is_dir()) {
+        return run_batch(&options, &mut io::stdout().lock());
+    }
+    let path = options.paths.first().map(String::as_str);
+    let json = options.json;
+    let start = Instant::now();
 
-        // Could not find an unassigned variable and therefore must be true
-        if pick_var == 0 {
-            return true;
+    let dimacs = read_dimacs(path, options.strict)?;
+    let size = (dimacs.num_vars, dimacs.clause_count());
+    let mut warnings = Vec::new();
+    if dimacs.num_vars > dimacs.declared_vars {
+        warnings.push(format!(
+            "c warning: header declares {} variables but variable {} is used",
+            dimacs.declared_vars, dimacs.num_vars
+        ));
+    }
+    if let Some(declared) = dimacs.declared_clauses.filter(|&n| n != dimacs.clause_count()) {
+        warnings.push(format!(
+            "c warning: header declares {} clauses but {} were found",
+            declared,
+            dimacs.clause_count()
+        ));
+    }
+    for warning in warnings {
+        // JSON output has to stay parseable
+        if json {
+            eprintln!("{}", warning);
+        } else {
+            println!("{}", warning);
         }
+    }
 
-        let try_positive_first = self.positives[pick_var] >= self.negatives[pick_var];
+    if options.proof_path.is_some() && !dimacs.xors.is_empty() {
+        return Err(AppError::Unsupported(
+            "--proof is not supported with XOR constraints".into(),
+        ));
+    }
 
-        let snapshot = self.history.len();
-        
-        let first_lit = if try_positive_first {
-            pick_var as i32
-        } else {
-            -(pick_var as i32)
+    if let Some(check_path) = &options.check_path {
+        return check_assignment(check_path, &dimacs, &options, start);
+    }
+
+    let mut assumptions = options.assumed.clone();
+    if let Some(path) = &options.assumptions_path {
+        let input = read_input(Some(path)).map_err(io_error(format!("cannot read {}", path)))?;
+        assumptions.extend(parse_assumptions(&input)?);
+    }
+    let num_vars = dimacs.num_vars;
+    if let Some(lit) = assumptions.iter().find(|lit| lit.var() as usize > num_vars) {
+        return Err(AppError::Unsupported(format!(
+            "assumption {} is over a variable the formula doesn't have",
+            lit
+        )));
+    }
+    let assumptions = options.assumes().then_some(assumptions);
+
+    if dimacs.top.is_some() {
+        if options.proof_path.is_some() {
+            return Err(AppError::Unsupported("--proof is not supported for WCNF input".into()));
+        }
+        if assumptions.is_some() {
+            return Err(AppError::Unsupported("--assume is not supported for WCNF input".into()));
+        }
+        if options.dump_path.is_some() {
+            return Err(AppError::Unsupported(
+                "--dump-conflict is not supported for WCNF input".into(),
+            ));
+        }
+        if !dimacs.xors.is_empty() {
+            return Err(AppError::Unsupported(
+                "XOR constraints are not supported for WCNF input".into(),
+            ));
+        }
+        return match Solver::solve_maxsat(&dimacs.clauses, &dimacs.soft, dimacs.num_vars) {
+            Some((cost, model)) => {
+                if json {
+                    let mut out = io::stdout().lock();
+                    write_json(&mut out, "sat", Some(&model), Some(cost), None, None)
+                        .map_err(io_error("cannot write output".into()))?;
+                } else {
+                    println!("o {}", cost);
+                    println!("s OPTIMUM FOUND");
+                    let mut out = BufWriter::new(io::stdout().lock());
+                    write_model(&mut out, &model, options.model_width)
+                        .and_then(|()| out.flush())
+                        .map_err(io_error("cannot write output".into()))?;
+                }
+                if let Some(model_path) = &options.model_path {
+                    save_model_table(model_path, &model)?;
+                }
+                if options.report {
+                    let status = format!("OPTIMUM FOUND (cost {})", cost);
+                    write_report(&mut io::stderr().lock(), &status, size, None, start.elapsed())
+                        .map_err(io_error("cannot write report".into()))?;
+                }
+                if options.verify {
+                    verify_model(&dimacs.clauses, &[], &model)?;
+                }
+                Ok(30)
+            }
+            None => {
+                if json {
+                    write_json(&mut io::stdout().lock(), "unsat", None, None, None, None)
+                        .map_err(io_error("cannot write output".into()))?;
+                } else {
+                    println!("s UNSATISFIABLE");
+                }
+                if options.report {
+                    let elapsed = start.elapsed();
+                    write_report(&mut io::stderr().lock(), "UNSATISFIABLE", size, None, elapsed)
+                        .map_err(io_error("cannot write report".into()))?;
+                }
+                Ok(20)
+            }
         };
+    }
+
+    // preprocessing rewrites the solver's copy of the clauses
+    let original = options.verify.then(|| dimacs.clauses.clone());
+    let mut solver = Solver::new(dimacs.clauses, dimacs.num_vars);
+    for xor in &dimacs.xors {
+        solver.add_xor(xor);
+    }
+    solver.set_independent_support(dimacs.independent_support);
+    if let Some(proof_path) = &options.proof_path {
+        let file =
+            File::create(proof_path).map_err(io_error(format!("cannot create {}", proof_path)))?;
+        solver.set_proof(Box::new(BufWriter::new(file)));
+    }
+    if let Some(dump_path) = &options.dump_path {
+        let file =
+            File::create(dump_path).map_err(io_error(format!("cannot create {}", dump_path)))?;
+        solver.set_conflict_dump(Box::new(BufWriter::new(file)));
+    }
+
+    if options.verbosity > 0 {
+        let verbosity = options.verbosity;
+        solver.on_progress(
+            PROGRESS_INTERVAL,
+            Box::new(move |progress| {
+                // on stderr under --json, like the header warnings; a lost
+                // progress line isn't worth stopping the solve for
+                let elapsed = start.elapsed();
+                let _ = if json {
+                    write_progress(&mut io::stderr().lock(), progress, elapsed, verbosity)
+                } else {
+                    write_progress(&mut io::stdout().lock(), progress, elapsed, verbosity)
+                };
+            }),
+        );
+    }
+
+    let interrupt = Arc::new(AtomicBool::new(false));
+    solver.set_interrupt(interrupt.clone());
+    #[cfg(unix)]
+    sigint::install(interrupt);
 
-        if self.propagate(first_lit) && self.solve() {
-            return true;
+    let result = match &assumptions {
+        Some(assumptions) => solver.solve_under_assumptions(assumptions),
+        None => solver.solve_with_limit(u64::MAX),
+    };
+    solver.flush_proof().map_err(io_error("cannot write proof".into()))?;
+    if let Some(dump_path) = &options.dump_path {
+        let dumped = solver
+            .finish_conflict_dump()
+            .map_err(io_error(format!("cannot write {}", dump_path)))?;
+        if !dumped {
+            let note = format!("c no conflict happened, {} is left empty", dump_path);
+            if json {
+                eprintln!("{}", note);
+            } else {
+                println!("{}", note);
+            }
         }
+    }
 
-        self.backtrack(snapshot);
-        
-        if self.propagate(-first_lit) && self.solve() {
-            return true;
+    let model = solver.model();
+    // the assumptions behind an UNSAT answer, empty if the formula is
+    // unsatisfiable without them
+    let core =
+        (assumptions.is_some() && result == SolveResult::Unsat).then(|| solver.unsat_core());
+    if let (Some(model_path), Some(model)) = (&options.model_path, &model) {
+        save_model_table(model_path, model)?;
+    }
+    if json {
+        let status = match result {
+            SolveResult::Sat => "sat",
+            SolveResult::Unsat => "unsat",
+            SolveResult::Unknown => "unknown",
+        };
+        let (model, core, stats) = (model.as_deref(), core.as_deref(), Some(solver.stats()));
+        write_json(&mut io::stdout().lock(), status, model, None, core, stats)
+            .map_err(io_error("cannot write output".into()))?;
+    } else {
+        if options.show_stats {
+            write_stats(&mut io::stdout().lock(), solver.stats())
+                .map_err(io_error("cannot write output".into()))?;
         }
+        let mut out = BufWriter::new(io::stdout().lock());
+        let (model, core) = (model.as_deref(), core.as_deref());
+        write_outcome(&mut out, result, model, core, options.model_width)
+            .and_then(|()| out.flush())
+            .map_err(io_error("cannot write output".into()))?;
+    }
+    // on stderr, where it can't get mixed up with the DIMACS output
+    if options.report {
+        let status = match result {
+            SolveResult::Sat => "SATISFIABLE",
+            SolveResult::Unsat => "UNSATISFIABLE",
+            SolveResult::Unknown => "UNKNOWN",
+        };
+        let (stats, elapsed) = (Some(solver.stats()), start.elapsed());
+        write_report(&mut io::stderr().lock(), status, size, stats, elapsed)
+            .map_err(io_error("cannot write report".into()))?;
+    }
 
-        self.backtrack(entry_snapshot);
-        false
+    if let (Some(clauses), Some(model)) = (&original, &model) {
+        verify_model(clauses, &dimacs.xors, model)?;
     }
+    Ok(match result {
+        SolveResult::Sat => 10,
+        SolveResult::Unsat => 20,
+        SolveResult::Unknown => 0,
+    })
+}
 
-    fn bcp(&mut self) -> bool {
-        let mut changed = true;
+fn main() {
+    match run(env::args().skip(1).collect()) {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
 
-        while changed {
-            changed = false;
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
 
-            for clause_idx in 0..self.clauses.len() {
-                let clause = &self.clauses[clause_idx];
-                
-                let mut unassigned_count = 0;
-                let mut last_unassigned = 0;
+    use sat_solver::write_dimacs;
 
-                for &lit in clause {
-                    match self.val(lit) {
-                        Some(true) => {
-                            unassigned_count = 2;
-                            break;
-                        }
-                        Some(false) => {}
-                        None => {
-                            unassigned_count += 1;
-                            last_unassigned = lit;
-                            if unassigned_count > 1 {
-                                break;
-                            }
-                        }
-                    }
-                }
+    use super::*;
 
-                if unassigned_count == 0 {
-                    return false;
-                }
+    #[test]
+    fn read_input_reads_named_file() {
+        let path = env::temp_dir().join(format!("sat_solver_read_{}.cnf", process::id()));
+        std::fs::write(&path, "p cnf 1 1\n1 0\n").unwrap();
+        let input = read_input(path.to_str());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(input.unwrap(), "p cnf 1 1\n1 0\n");
+    }
+
+    #[test]
+    fn read_input_reports_missing_file() {
+        let err = read_input(Some("/nonexistent/sat_solver/input.cnf")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn model_lines_are_wrapped_and_terminated() {
+        let mut model = vec![false];
+        model.extend((1..=40).map(|var| var % 3 != 0));
+        let mut out = Vec::new();
+        write_model(&mut out, &model, MODEL_WIDTH).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|line| line.starts_with("v ") && line.len() <= 80));
+        let lits: Vec<i32> = lines
+            .iter()
+            .flat_map(|line| line[1..].split_whitespace())
+            .map(|token| token.parse().unwrap())
+            .collect();
+        let expected: Vec<i32> = (1..=40).map(|v| if v % 3 != 0 { v } else { -v }).collect();
+        assert_eq!(lits[..40], expected[..]);
+        assert_eq!(lits[40..], [0]);
+    }
 
-                if unassigned_count == 1 {
-                    if self.val(last_unassigned).is_none() {
-                        if !self.propagate(last_unassigned) {
-                            return false;
-                        }
-                        changed = true;
+    #[test]
+    fn model_width_bounds_every_line() {
+        let model: Vec<bool> = (0..=5000).map(|var| var > 0 && var % 7 < 3).collect();
+        for width in [12, 30, MODEL_WIDTH, 200] {
+            let mut out = Vec::new();
+            write_model(&mut out, &model, width).unwrap();
+            let out = String::from_utf8(out).unwrap();
+
+            let lines: Vec<&str> = out.lines().collect();
+            assert!(lines.iter().all(|line| line.starts_with("v ") && line.len() <= width));
+            let (last, rest) = lines.split_last().unwrap();
+            assert!(last.ends_with(" 0"));
+            assert!(rest.iter().all(|line| !line.ends_with(" 0")));
+            let mut parsed = vec![false];
+            for token in lines.iter().flat_map(|line| line[1..].split_whitespace()) {
+                match token.parse::<i64>().unwrap() {
+                    0 => break,
+                    lit => {
+                        assert_eq!(lit.unsigned_abs() as usize, parsed.len());
+                        parsed.push(lit > 0);
                     }
                 }
             }
+            assert_eq!(parsed, model);
         }
-        true
+
+        // a literal wider than the line still goes out whole
+        let mut out = Vec::new();
+        write_model(&mut out, &[false, true, false], 3).unwrap();
+        assert_eq!(out, b"v 1\nv -2\nv 0\n");
     }
 
-    fn propagate(&mut self, lit: Lit) -> bool {
-        if let Some(val) = self.val(lit) {
-            return val;
+    #[test]
+    fn model_width_option_takes_a_number() {
+        assert_eq!(Options::parse(args(&[])).unwrap().model_width, MODEL_WIDTH);
+        let options = Options::parse(args(&["--model-width", "40", "f.cnf"])).unwrap();
+        assert_eq!((options.model_width, &options.paths[..]), (40, &["f.cnf".to_string()][..]));
+        for bad in [&["--model-width"][..], &["--model-width", "wide"], &["--model-width", "-1"]] {
+            assert!(matches!(Options::parse(args(bad)), Err(AppError::Usage)), "{:?}", bad);
         }
+    }
 
-        self.assign_lit(lit);
+    #[test]
+    fn empty_model_is_a_single_terminated_line() {
+        let mut out = Vec::new();
+        write_model(&mut out, &[false], MODEL_WIDTH).unwrap();
+        assert_eq!(out, b"v 0\n");
+    }
 
-        let neg_lit = -lit;
-        let neg_idx = self.lit_index(neg_lit);
+    #[test]
+    fn stats_are_comment_lines() {
+        let stats = Stats {
+            decisions: 7,
+            conflicts: 3,
+            agility: 0.5,
+            ..Stats::default()
+        };
+        let mut out = Vec::new();
+        write_stats(&mut out, &stats).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 8);
+        assert!(out.lines().all(|line| line.starts_with("c ")));
+        assert!(out.contains("c decisions    7\n"));
+        assert!(out.contains("c conflicts    3\n"));
+        assert!(out.ends_with("c agility      0.50\n"));
+    }
 
-        let mut i = 0;
-        while i < self.watch_lists[neg_idx].len() {
-            let clause_idx = self.watch_lists[neg_idx][i];
-            let clause = &self.clauses[clause_idx];
+    #[test]
+    fn json_has_status_model_and_stats() {
+        let stats = Stats {
+            decisions: 2,
+            ..Stats::default()
+        };
+        let mut out = Vec::new();
+        write_json(&mut out, "sat", Some(&[false, true, false]), None, None, Some(&stats)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"status\":\"sat\",\"model\":[1,-2],\"stats\":{\"decisions\":2,\
+             \"propagations\":0,\"conflicts\":0,\"restarts\":0,\"learned\":0,\"deleted\":0,\
+             \"average_lbd\":0.00,\"agility\":0.00}}\n"
+        );
 
-            let (first, second) = self.watches[clause_idx];
+        let mut out = Vec::new();
+        write_json(&mut out, "unsat", None, None, None, None).unwrap();
+        assert_eq!(out, b"{\"status\":\"unsat\"}\n");
+        let mut out = Vec::new();
+        let core = [Lit::from(3), Lit::from(-5)];
+        write_json(&mut out, "unsat", None, None, Some(&core), None).unwrap();
+        assert_eq!(out, b"{\"status\":\"unsat\",\"core\":[3,-5]}\n");
+        let mut out = Vec::new();
+        write_json(&mut out, "sat", Some(&[false, false]), Some(7), None, None).unwrap();
+        assert_eq!(out, b"{\"status\":\"sat\",\"cost\":7,\"model\":[-1]}\n");
+    }
 
-            let (current, other) = if clause[first] == neg_lit {
-                (first, second)
-            } else {
-                (second, first)
-            };
+    #[test]
+    fn verify_catches_a_corrupted_model() {
+        let dimacs = Dimacs::parse("p cnf 3 2\n1 -2 0\n2 3 0\nx 1 3 0\n").unwrap();
+        let check = |model: &[bool]| match verify_model(&dimacs.clauses, &dimacs.xors, model) {
+            Ok(()) => None,
+            Err(AppError::Verify(message)) => Some(message),
+            Err(e) => panic!("unexpected error {:?}", e),
+        };
+        assert_eq!(check(&[false, true, true, false]), None);
+        assert_eq!(
+            check(&[false, false, true, false]).unwrap(),
+            "model falsifies clause 1: 1 -2 0"
+        );
+        assert_eq!(
+            check(&[false, true, false, true]).unwrap(),
+            "model falsifies XOR constraint 1: x 1 3 0"
+        );
 
-            if let Some(true) = self.val(clause[other]) {
-                i += 1;
-                continue;
-            }
+        // a model the solver found passes
+        let mut solver = Solver::new(dimacs.clauses.clone(), dimacs.num_vars);
+        solver.add_xor(&dimacs.xors[0]);
+        assert!(solver.solve());
+        assert_eq!(check(&solver.model().unwrap()), None);
+    }
 
-            let mut found_new_watch = false;
-            for j in 2..clause.len() {
-                if j == current || j == other {
-                    continue;
-                }
-                if self.val(clause[j]) != Some(false) {
-                    let new_idx = self.lit_index(clause[j]);
-                    if current == first {
-                        self.watches[clause_idx] = (j, second);
-                    } else {
-                        self.watches[clause_idx] = (first, j);
-                    }
-                    self.watch_lists[neg_idx].swap_remove(i);
-                    self.watch_lists[new_idx].push(clause_idx);
-    
-                    found_new_watch = true;
-                    break;
-                }
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
 
+    #[test]
+    fn unreadable_input_is_an_io_error() {
+        let path = env::temp_dir().join(format!("sat_solver_missing_{}.cnf", process::id()));
+        match run(args(&[path.to_str().unwrap()])) {
+            Err(AppError::Io { context, source }) => {
+                assert_eq!(context, format!("cannot read {}", path.display()));
+                assert_eq!(source.kind(), io::ErrorKind::NotFound);
             }
-            if !found_new_watch {
-                i += 1;
-            }
-        }   
-        true
-    }
- 
-    fn backtrack(&mut self, saved_len: usize) {
-        while self.history.len() > saved_len {
-            let lit = self.history.pop().unwrap();
-            let var_idx = lit.abs() as usize;
-            self.assignment[var_idx] = None;
+            other => panic!("expected an IO error, got {:?}", other),
         }
     }
 
-    #[inline(always)]
-    fn assign_lit(&mut self, lit: Lit) {
-        let var_idx = lit.abs() as usize;
-        if self.assignment[var_idx].is_none() {
-            let val = lit > 0;
-            self.assignment[var_idx] = Some(val);
-            self.history.push(lit);
+    #[test]
+    fn unknown_options_are_usage_errors() {
+        for bad in [&["--bogus"][..], &["-x", "f.cnf"], &["--proof"]] {
+            assert!(matches!(Options::parse(args(bad)), Err(AppError::Usage)), "{:?}", bad);
         }
+        let options = Options::parse(args(&["--stats", "-"])).unwrap();
+        assert!(options.show_stats);
+        assert_eq!(options.verbosity, 0);
+        assert_eq!(options.paths, ["-"]);
     }
 
-}
+    #[test]
+    fn batch_reports_each_instance_and_the_counts() {
+        let dir = env::temp_dir().join(format!("sat_solver_batch_{}", process::id()));
+        fs::create_dir_all(dir.join("more")).unwrap();
+        let write = |name: &str, text: &str| {
+            let path = dir.join(name);
+            fs::write(&path, text).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+        let sat = write("sat.cnf", "p cnf 2 2\n1 2 0\n-1 0\n");
+        let unsat = write("more/unsat.cnf", "p cnf 1 2\n1 0\n-1 0\n");
+        let wcnf = write("more/opt.wcnf", "p wcnf 2 3 10\n10 1 2 0\n3 -1 0\n4 -2 0\n");
+        let broken = write("broken.cnf", "p cnf 1 1\n1\n");
 
+        let more = dir.join("more").to_string_lossy().into_owned();
+        let options = Options::parse(args(&["--verify", &sat, &more, &broken])).unwrap();
+        let mut out = Vec::new();
+        let code = run_batch(&options, &mut out).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
 
-fn main() {
-    let stdin = io::stdin();
-    let mut handle = stdin.lock();
-    let mut buffer = String::new();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 5, "{}", out);
+        assert_eq!(lines[0], format!("{}: SAT", sat));
+        assert_eq!(lines[1], format!("{}: OPTIMUM 3", wcnf));
+        assert_eq!(lines[2], format!("{}: UNSAT", unsat));
+        assert!(lines[3].starts_with(&format!("{}: error: ", broken)), "{}", lines[3]);
+        let summary = "c 4 instances: 1 SAT, 1 UNSAT, 1 OPTIMUM, 0 UNKNOWN, 1 errors in ";
+        assert!(lines[4].starts_with(summary), "{}", lines[4]);
+        assert_eq!(code, 1);
+
+        let options = Options::parse(args(&["--json", &sat, &unsat])).unwrap();
+        assert!(matches!(run_batch(&options, &mut Vec::new()), Err(AppError::Unsupported(_))));
+    }
+
+    #[test]
+    fn report_lines_up_status_and_counters() {
+        let stats = Stats {
+            decisions: 12,
+            conflicts: 3,
+            ..Stats::default()
+        };
+        let mut out = Vec::new();
+        let elapsed = Duration::from_millis(1250);
+        write_report(&mut out, "SATISFIABLE", (5, 9), Some(&stats), elapsed).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "status        SATISFIABLE");
+        assert_eq!(lines[3], "decisions     12");
+        assert_eq!(lines[7], "time          1.250s");
 
-    handle.read_to_string(&mut buffer).unwrap();
+        let mut out = Vec::new();
+        write_report(&mut out, "UNSATISFIABLE", (1, 2), None, elapsed).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out,
+            "status     UNSATISFIABLE\nvariables  1\nclauses    2\ntime       1.250s\n"
+        );
+    }
 
-    let mut clauses: Vec<Clause> = Vec::new();
-    let mut num_vars = 0;
+    #[test]
+    fn model_table_has_a_line_per_variable() {
+        let mut out = Vec::new();
+        write_model_table(&mut out, &[false, true, false, true]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "1 true\n2 false\n3 true\n");
 
-    let mut current_clause: Clause = Vec::new();
+        // a full run writes it next to the usual output, parsed back here
+        let dir = env::temp_dir();
+        let input = dir.join(format!("sat_solver_table_{}.cnf", process::id()));
+        let table = dir.join(format!("sat_solver_table_{}.txt", process::id()));
+        fs::write(&input, "p cnf 5 3\n1 -2 0\n2 3 0\n-5 0\n").unwrap();
+        let code = run(args(&[
+            "--model-out",
+            table.to_str().unwrap(),
+            input.to_str().unwrap(),
+        ]));
+        let written = fs::read_to_string(&table).unwrap();
+        fs::remove_file(&input).unwrap();
+        fs::remove_file(&table).unwrap();
+        assert_eq!(code.unwrap(), 10);
 
-    for line in buffer.lines() {
-        let l = line.trim();
-        if l.is_empty() || l.starts_with("c") { continue; }
-        if l.starts_with("p") {
-            let parts: Vec<&str> = l.split_whitespace().collect();
-            num_vars = parts[2].parse().unwrap();
-            continue;
+        let mut model = vec![false];
+        for (line, expected_var) in written.lines().zip(1..) {
+            let (var, value) = line.split_once(' ').unwrap();
+            assert_eq!(var.parse::<usize>().unwrap(), expected_var);
+            model.push(value.parse().unwrap());
+        }
+        assert_eq!(model.len(), 6);
+        let clauses = Dimacs::parse("p cnf 5 3\n1 -2 0\n2 3 0\n-5 0\n").unwrap().clauses;
+        assert!(verify_model(&clauses, &[], &model).is_ok());
+    }
+
+    #[test]
+    fn core_is_a_terminated_comment_line() {
+        let mut out = Vec::new();
+        write_core(&mut out, &[Lit::from(3), Lit::from(-5)]).unwrap();
+        assert_eq!(out, b"c core 3 -5 0\n");
+        let mut out = Vec::new();
+        write_core(&mut out, &[]).unwrap();
+        assert_eq!(out, b"c core 0\n");
+    }
+
+    #[test]
+    fn assumption_files_query_the_same_formula() {
+        let dir = env::temp_dir();
+        let write = |name: &str, text: &str| {
+            let path = dir.join(format!("sat_solver_{}_{}", process::id(), name));
+            fs::write(&path, text).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+        let formula = write("assume.cnf", "p cnf 3 2\n-1 2 0\n-2 -3 0\n");
+        let sat = write("sat.assume", "1\n-3 0\n");
+        let unsat = write("unsat.assume", "c both\n1 3 0\n");
+        let far = write("far.assume", "1 4 0\n");
+
+        let sat_code = run(args(&["--assume", &sat, &formula]));
+        let unsat_code = run(args(&["--assume", &unsat, &formula]));
+        let far_code = run(args(&["--assume", &far, &formula]));
+        for path in [&formula, &sat, &unsat, &far] {
+            fs::remove_file(path).unwrap();
+        }
+        assert_eq!(sat_code.unwrap(), 10);
+        assert_eq!(unsat_code.unwrap(), 20);
+        assert!(matches!(far_code, Err(AppError::Unsupported(_))));
+    }
+
+    #[test]
+    fn verbosity_comes_from_v_and_vv() {
+        for (given, verbosity) in [(&["-v"][..], 1), (&["-vv"], 2), (&["-vv", "-v"], 2)] {
+            let options = Options::parse(args(given)).unwrap();
+            assert_eq!(options.verbosity, verbosity, "{:?}", given);
+            assert!(options.paths.is_empty());
         }
+        assert!(matches!(Options::parse(args(&["-vvv"])), Err(AppError::Usage)));
+    }
+
+    #[test]
+    fn progress_line_adds_the_decision_under_vv() {
+        let stats = Stats {
+            conflicts: 640,
+            restarts: 3,
+            ..Stats::default()
+        };
+        let progress = Progress {
+            stats: &stats,
+            learned_clauses: 21,
+            decision: Some(Lit::from(-4)),
+        };
+        let line = |verbosity| {
+            let mut out = Vec::new();
+            let elapsed = Duration::from_millis(5250);
+            write_progress(&mut out, &progress, elapsed, verbosity).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let v = "c progress 5.2s: 640 conflicts, 3 restarts, 21 learned clauses";
+        assert_eq!(line(1), format!("{}\n", v));
+        assert_eq!(line(2), format!("{}, deciding -4\n", v));
+    }
 
-        let nums: Vec<i32> = l.split_whitespace()
-            .map(|x| x.parse().unwrap())
+    // Puts `holes + 1` pigeons into `holes` holes, one each: unsatisfiable,
+    // and only after many conflicts.
+    fn pigeonhole(holes: usize) -> (Vec<Clause>, usize) {
+        let pigeons = holes + 1;
+        let var = |p: usize, h: usize| Lit::from((p * holes + h + 1) as LitInt);
+        let mut formula: Vec<Clause> = (0..pigeons)
+            .map(|p| (0..holes).map(|h| var(p, h)).collect())
             .collect();
-        
-        for n in nums {
-            if n == 0 {
-                if !current_clause.is_empty() {
-                    clauses.push(current_clause.clone());
-                    current_clause.clear();
+        for h in 0..holes {
+            for p in 0..pigeons {
+                for q in p + 1..pigeons {
+                    formula.push(vec![-var(p, h), -var(q, h)]);
                 }
             }
-            else {
-                current_clause.push(n);
-            }
-        } 
+        }
+        (formula, pigeons * holes)
     }
 
+    #[test]
+    fn long_run_writes_progress_lines() {
+        // thousands of conflicts; the limit stops it well before
+        let (formula, num_vars) = pigeonhole(6);
+        let mut solver = Solver::new(formula, num_vars);
+        let out = Rc::new(RefCell::new(Vec::new()));
+        let sink = out.clone();
+        let start = Instant::now();
+        solver.on_progress(
+            Duration::ZERO,
+            Box::new(move |progress| {
+                let mut sink = sink.borrow_mut();
+                write_progress(&mut *sink, progress, start.elapsed(), 1).unwrap();
+            }),
+        );
+        assert_eq!(solver.solve_with_limit(500), SolveResult::Unknown);
 
-    let mut solver = Solver::new(clauses, num_vars);
+        let out = String::from_utf8(out.borrow().clone()).unwrap();
+        assert!(!out.is_empty());
+        assert!(out.lines().all(|line| line.starts_with("c progress ")), "{}", out);
+    }
+    const FORMULA: &str = "p cnf 4 4\n1 2 0\n-1 3 0\n-2 -3 0\n1 4 0\n";
 
-    if solver.solve() {
-        println!("SATISFIABLE");
-        for i in 1..=num_vars {
-            let lit = i as i32;
-            match solver.val(lit) {
-                Some(true) => print!(" {}", i),
-                Some(false) => print!(" -{}", i),
-                None => print!(" {}", i),
-            }
+    fn check(assignment: &str) -> Result<Option<String>, AppError> {
+        let dimacs = Dimacs::parse(FORMULA).unwrap();
+        let assigned = parse_assignment(assignment, dimacs.num_vars)?;
+        check_partial(&dimacs.clauses, &dimacs.xors, &assigned)
+    }
+
+    // Writes `contents` to a file of its own for the command-line tests.
+    fn temp_file(name: &str, contents: &str) -> String {
+        let path = env::temp_dir().join(format!("sat_solver-{}-{}", process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn model_passes() {
+        assert!(check("1 3 -2 4 0\n").unwrap().is_none());
+        // the solver's own output
+        assert!(check("s SATISFIABLE\nv 1 -2\nv 3 -4 0\n").unwrap().is_none());
+    }
+
+    #[test]
+    fn wrong_model_names_the_clause() {
+        let failure = check("v 1 2 3 0").unwrap();
+        assert_eq!(failure.as_deref(), Some("clause 3: -2 -3 0"));
+        let failure = check("-1 -2 -3 4 0").unwrap();
+        assert_eq!(failure.as_deref(), Some("clause 1: 1 2 0"));
+    }
+
+    #[test]
+    fn unset_variables() {
+        // 4 only occurs in clause 4, which 1 satisfies
+        assert!(check("1 -2 3 0").unwrap().is_none());
+        let unset = |assignment: &str| match check(assignment) {
+            Err(AppError::Unsupported(message)) => message,
+            _ => panic!("{} is accepted", assignment),
+        };
+        // clause 3 needs 2, and clause 4 needs 4
+        assert_eq!(unset("1 3 0"), "assignment leaves variable 2 unset");
+        assert_eq!(unset("-1 2 -3 0"), "assignment leaves variable 4 unset");
+    }
+
+    #[test]
+    fn check_model_command_line() {
+        let formula = temp_file("check.cnf", FORMULA);
+        let good = temp_file("good.model", "v 1 -2 3 0\n");
+        let bad = temp_file("bad.model", "1 2 3 0\n");
+        let args = |model: &str, extra: &[&str]| {
+            let mut args = vec!["--check-model".to_string(), model.to_string()];
+            args.extend(extra.iter().map(|arg| arg.to_string()));
+            args.push(formula.clone());
+            args
+        };
+        assert_eq!(run(args(&good, &[])).unwrap(), 10);
+        assert_eq!(run(args(&bad, &[])).unwrap(), 20);
+        for flag in ["-v", "--stats", "--verify"] {
+            assert!(matches!(run(args(&good, &[flag])), Err(AppError::Unsupported(_))));
         }
-        println!();
-    } else {
-        println!("UNSATISFIABLE")
+        for path in [formula, good, bad] {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    #[test]
+    fn outcome_is_the_status_line_and_what_goes_with_it() {
+        let outcome = |result, model: Option<&[bool]>, core: Option<&[Lit]>| {
+            let mut out = Vec::new();
+            write_outcome(&mut out, result, model, core, MODEL_WIDTH).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let model = [false, true, false];
+        assert_eq!(outcome(SolveResult::Sat, Some(&model), None), "s SATISFIABLE\nv 1 -2 0\n");
+        assert_eq!(outcome(SolveResult::Unsat, None, None), "s UNSATISFIABLE\n");
+        let core = [Lit::from(-3)];
+        assert_eq!(
+            outcome(SolveResult::Unsat, None, Some(&core)),
+            "c core -3 0\ns UNSATISFIABLE\n"
+        );
+        assert_eq!(outcome(SolveResult::Unknown, None, None), "s UNKNOWN\n");
+    }
+
+    #[test]
+    fn interrupted_solve_is_unknown() {
+        // the flag Ctrl-C sets is already up, so the search stops at its
+        // first conflict
+        let (formula, num_vars) = pigeonhole(5);
+        let interrupt = Arc::new(AtomicBool::new(true));
+        let mut solver = Solver::new(formula.clone(), num_vars);
+        solver.set_interrupt(interrupt.clone());
+        assert_eq!(solver.solve_with_limit(u64::MAX), SolveResult::Unknown);
+        assert_eq!(solver.model(), None);
+        let mut out = Vec::new();
+        write_stats(&mut out, solver.stats()).unwrap();
+        write_outcome(&mut out, SolveResult::Unknown, None, None, MODEL_WIDTH).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("c conflicts    1\n") && out.ends_with("\ns UNKNOWN\n"), "{}", out);
+
+        // and a batch instance is counted as unknown instead of failing
+        let path = env::temp_dir().join(format!("sat_solver_interrupt_{}.cnf", process::id()));
+        let mut cnf = Vec::new();
+        write_dimacs(&formula, num_vars, &mut cnf).unwrap();
+        fs::write(&path, cnf).unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let verdict = solve_instance(&path, &Options::default(), &interrupt);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(verdict, Ok(Verdict::Unknown)));
+    }
+
+    #[test]
+    fn assume_takes_literals_one_at_a_time() {
+        let options = Options::parse(args(&["--assume", "3", "--assume", "-5", "f.cnf"])).unwrap();
+        assert_eq!(options.assumed, [Lit::from(3), Lit::from(-5)]);
+        assert_eq!((options.assumptions_path, &options.paths[..]), (None, &["f.cnf".into()][..]));
+        let min = LitInt::MIN.to_string();
+        for bad in [&["--assume", "0"][..], &["--assume", &min], &["--assume"]] {
+            assert!(matches!(Options::parse(args(bad)), Err(AppError::Usage)), "{:?}", bad);
+        }
+
+        let path = env::temp_dir().join(format!("sat_solver_assume_{}.cnf", process::id()));
+        fs::write(&path, "p cnf 3 2\n-1 2 0\n-2 -3 0\n").unwrap();
+        let formula = path.to_string_lossy().into_owned();
+        let sat = run(args(&["--assume", "1", "--assume", "-3", &formula]));
+        let unsat = run(args(&["--assume", "1", "--assume", "3", &formula]));
+        let far = run(args(&["--assume", "1", "--assume", "-4", &formula]));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(sat.unwrap(), 10);
+        assert_eq!(unsat.unwrap(), 20);
+        assert!(matches!(far, Err(AppError::Unsupported(_))));
+
+        // the core run() prints for the contradictory pair
+        let dimacs = Dimacs::parse("p cnf 3 2\n-1 2 0\n-2 -3 0\n").unwrap();
+        let mut solver = Solver::new(dimacs.clauses, dimacs.num_vars);
+        let assumed = Options::parse(args(&["--assume", "1", "--assume", "3"])).unwrap().assumed;
+        assert_eq!(solver.solve_under_assumptions(&assumed), SolveResult::Unsat);
+        let mut core = solver.unsat_core();
+        core.sort();
+        assert_eq!(core, assumed);
+        let mut out = Vec::new();
+        write_outcome(&mut out, SolveResult::Unsat, None, Some(&core), MODEL_WIDTH).unwrap();
+        assert_eq!(out, b"c core 1 3 0\ns UNSATISFIABLE\n");
     }
-    
-    
 }
@@ -0,0 +1,112 @@
+// Weighted MaxSAT on top of assumption-based solving.
+
+use crate::{check_model, Clause, Lit, SolveResult, Solver, Var};
+
+impl Solver {
+    /// Finds an assignment over `num_vars` variables that satisfies every
+    /// `hard` clause and maximizes the total weight of the satisfied `soft`
+    /// clauses. Returns its cost, the weight of the soft clauses it falsifies,
+    /// with the model indexed like `model()`; None if the hard clauses are
+    /// unsatisfiable.
+    ///
+    /// This is the WPM1 core-guided scheme (Fu-Malik with weights): each soft
+    /// clause gets a selector that is assumed true, and every unsatisfiable
+    /// core of selectors costs at least the lightest weight w in it. Each
+    /// clause of the core keeps weight w and gains a fresh relaxation
+    /// variable, exactly one of which may be true, while any weight above w
+    /// is split off into an unrelaxed copy. Every round solves with a new
+    /// solver.
+    pub fn solve_maxsat(
+        hard: &[Clause],
+        soft: &[(u64, Clause)],
+        num_vars: usize,
+    ) -> Option<(u64, Vec<bool>)> {
+        // soft clauses with the relaxation literals added so far, and the
+        // exactly-one constraints over those
+        let mut relaxed: Vec<(u64, Clause)> =
+            soft.iter().filter(|(w, _)| *w > 0).cloned().collect();
+        let mut constraints: Vec<Clause> = Vec::new();
+        let mut last_var = num_vars;
+
+        loop {
+            let first_selector = last_var + 1;
+            let mut clauses: Vec<Clause> = hard.to_vec();
+            clauses.extend(constraints.iter().cloned());
+            let mut selectors = Vec::with_capacity(relaxed.len());
+            for (i, (_, clause)) in relaxed.iter().enumerate() {
+                let selector = Lit::new((first_selector + i) as Var, true);
+                let mut clause = clause.clone();
+                clause.push(-selector);
+                clauses.push(clause);
+                selectors.push(selector);
+            }
+
+            let mut solver = Solver::new(clauses, last_var + relaxed.len());
+            if solver.solve_under_assumptions(&selectors) == SolveResult::Sat {
+                let mut model = solver.model().unwrap();
+                model.truncate(num_vars + 1);
+                let cost = soft
+                    .iter()
+                    .filter(|(_, clause)| !check_model(std::slice::from_ref(clause), &model))
+                    .map(|(w, _)| w)
+                    .sum();
+                return Some((cost, model));
+            }
+
+            let core: Vec<usize> = solver
+                .unsat_core()
+                .iter()
+                .map(|lit| lit.var() as usize - first_selector)
+                .collect();
+            if core.is_empty() {
+                return None;
+            }
+            let min = core.iter().map(|&i| relaxed[i].0).min().unwrap();
+
+            let mut relaxations = Vec::with_capacity(core.len());
+            for &i in &core {
+                if relaxed[i].0 > min {
+                    relaxed.push((relaxed[i].0 - min, relaxed[i].1.clone()));
+                    relaxed[i].0 = min;
+                }
+                last_var += 1;
+                let relaxation = Lit::new(last_var as Var, true);
+                relaxed[i].1.push(relaxation);
+                relaxations.push(relaxation);
+            }
+            for (j, &a) in relaxations.iter().enumerate() {
+                for &b in &relaxations[j + 1..] {
+                    constraints.push(vec![-a, -b]);
+                }
+            }
+            constraints.push(relaxations);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{check_model, Dimacs, Solver};
+
+    #[test]
+    fn tiny_wcnf_optimum() {
+        // one of 1 and 2 must hold; falsifying (-1) costs 3, whereas
+        // falsifying (-2) and (1) costs 2 + 4
+        let text = "p wcnf 2 4 10\n10 1 2 0\n3 -1 0\n2 -2 0\n4 1 0\n";
+        let dimacs = Dimacs::parse(text).unwrap();
+        assert_eq!(dimacs.top, Some(10));
+        assert_eq!(dimacs.soft.len(), 3);
+
+        let (cost, model) =
+            Solver::solve_maxsat(&dimacs.clauses, &dimacs.soft, dimacs.num_vars).unwrap();
+        assert_eq!(cost, 3);
+        assert_eq!(model, vec![false, true, false]);
+        assert!(check_model(&dimacs.clauses, &model));
+    }
+
+    #[test]
+    fn unsatisfiable_hard_clauses() {
+        let dimacs = Dimacs::parse("p wcnf 1 3 5\n5 1 0\n5 -1 0\n1 1 0\n").unwrap();
+        assert_eq!(Solver::solve_maxsat(&dimacs.clauses, &dimacs.soft, dimacs.num_vars), None);
+    }
+}
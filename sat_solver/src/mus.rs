@@ -0,0 +1,92 @@
+// Minimal unsatisfiable subsets of the clauses, on top of assumption-based
+// solving.
+
+use crate::{Lit, SolveResult, Solver, Var};
+
+impl Solver {
+    /// Returns the indices into `original_clauses()` of a minimal
+    /// unsatisfiable subset (MUS): clauses that are unsatisfiable together,
+    /// while leaving out any one of them makes the rest satisfiable. Empty if
+    /// the formula is satisfiable, or unsatisfiable through its XOR
+    /// constraints alone, which always count as part of the formula.
+    ///
+    /// Each clause gets a selector variable assumed true to switch it on, and
+    /// clauses are deleted one at a time: a clause stays only if the others
+    /// are satisfiable without it. Each unsatisfiable check also drops every
+    /// clause outside its core of selectors. One solver keeps its learned
+    /// clauses across all checks. A check stopped by an interrupt keeps its
+    /// clause, so the subset is then unsatisfiable but maybe not minimal.
+    pub fn compute_mus(&mut self) -> Vec<usize> {
+        let first_selector = self.num_vars + 1;
+        let selector = |i: usize| Lit::new((first_selector + i) as Var, true);
+        let clauses = self
+            .original_clauses
+            .iter()
+            .enumerate()
+            .map(|(i, clause)| {
+                let mut clause = clause.clone();
+                clause.push(-selector(i));
+                clause
+            })
+            .collect();
+        let mut solver = Solver::new(clauses, self.num_vars + self.original_clauses.len());
+        solver.xors = self.xors.clone();
+        if let Some(flag) = &self.interrupt {
+            solver.set_interrupt(flag.clone());
+        }
+
+        // indices of the clauses still in the subset, in ascending order
+        let in_core = |solver: &Solver, subset: &[usize]| -> Vec<usize> {
+            let core = solver.unsat_core();
+            subset.iter().copied().filter(|&i| core.contains(&selector(i))).collect()
+        };
+        let all: Vec<usize> = (0..self.original_clauses.len()).collect();
+        let assumptions: Vec<Lit> = all.iter().map(|&i| selector(i)).collect();
+        if solver.solve_under_assumptions(&assumptions) != SolveResult::Unsat {
+            return Vec::new();
+        }
+        let mut mus = in_core(&solver, &all);
+
+        // the clauses before position i are known to be needed, and so are
+        // in every core of the subset
+        let mut i = 0;
+        while i < mus.len() {
+            let rest: Vec<usize> = mus.iter().copied().filter(|&j| j != mus[i]).collect();
+            let assumptions: Vec<Lit> = rest.iter().map(|&j| selector(j)).collect();
+            match solver.solve_under_assumptions(&assumptions) {
+                SolveResult::Unsat => mus = in_core(&solver, &rest),
+                SolveResult::Sat | SolveResult::Unknown => i += 1,
+            }
+        }
+        mus
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::clauses;
+    use crate::{Clause, Solver};
+
+    #[test]
+    fn mus_is_minimal() {
+        // the four clauses over 1 and 2 are the only unsatisfiable subset
+        let formula = clauses(&[&[1, 2], &[3, 4], &[-1, 2], &[-3], &[1, -2], &[2, 4], &[-1, -2]]);
+        let mut solver = Solver::new(formula.clone(), 4);
+        let mus = solver.compute_mus();
+        assert_eq!(mus, vec![0, 2, 4, 6]);
+
+        let subset = |skip: usize| -> Vec<Clause> {
+            mus.iter().filter(|&&i| i != skip).map(|&i| formula[i].clone()).collect()
+        };
+        assert!(!Solver::new(subset(usize::MAX), 4).solve());
+        for &i in &mus {
+            assert!(Solver::new(subset(i), 4).solve(), "clause {} isn't needed", i);
+        }
+    }
+
+    #[test]
+    fn satisfiable_formula_has_no_mus() {
+        let mut solver = Solver::new(clauses(&[&[1, 2], &[-1]]), 2);
+        assert!(solver.compute_mus().is_empty());
+    }
+}
@@ -0,0 +1,70 @@
+// Racing differently configured solvers on the same formula.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::{Clause, Restarts, SolveResult, Solver, SolverConfig};
+
+// Configuration of portfolio member `i`: the first is the standard solver and
+// the others vary the seed, decay and restart policy and pace around it.
+fn member_config(i: usize) -> SolverConfig {
+    const DECAYS: [f64; 4] = [0.95, 0.9, 0.99, 0.85];
+    const RESTART_BASES: [u64; 4] = [100, 50, 300, 30];
+    SolverConfig {
+        var_decay: DECAYS[i % DECAYS.len()],
+        restarts: if i % 4 < 2 { Restarts::Lbd } else { Restarts::Luby },
+        restart_base: RESTART_BASES[(i / 2) % RESTART_BASES.len()],
+        pure_literals: i.is_multiple_of(2),
+        seed: i as u64,
+        ..SolverConfig::default()
+    }
+}
+
+/// Solves `clauses` on `n_threads` threads at once (at least one), each
+/// with its own copy of the formula and its own configuration, and returns
+/// the verdict of whichever finishes first. The others are told to stop at
+/// their next conflict, and all have finished when this returns.
+pub fn solve_portfolio(clauses: &[Clause], num_vars: usize, n_threads: usize) -> SolveResult {
+    let stop = Arc::new(AtomicBool::new(false));
+    let result = Mutex::new(SolveResult::Unknown);
+    thread::scope(|scope| {
+        for i in 0..n_threads.max(1) {
+            let stop = stop.clone();
+            let result = &result;
+            scope.spawn(move || {
+                let mut solver = Solver::with_config(clauses.to_vec(), num_vars, member_config(i));
+                solver.set_interrupt(stop.clone());
+                let verdict = solver.solve_with_limit(u64::MAX);
+                if verdict != SolveResult::Unknown {
+                    let mut slot = result.lock().unwrap();
+                    if *slot == SolveResult::Unknown {
+                        *slot = verdict;
+                    }
+                    stop.store(true, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    result.into_inner().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{pigeonhole, random_3sat};
+
+    #[test]
+    fn portfolio_agrees_with_a_single_solver() {
+        let (formula, num_vars) = pigeonhole(5);
+        assert_eq!(solve_portfolio(&formula, num_vars, 4), SolveResult::Unsat);
+        for seed in 0..6 {
+            let formula = random_3sat(50, 213, seed);
+            let mut single = Solver::new(formula.clone(), 50);
+            let expected = single.solve_with_limit(u64::MAX);
+            assert_eq!(solve_portfolio(&formula, 50, 4), expected, "seed {}", seed);
+        }
+        // no threads still means one solver
+        assert_eq!(solve_portfolio(&formula[..1], num_vars, 0), SolveResult::Sat);
+    }
+}
@@ -0,0 +1,550 @@
+// Clause database simplifications run before search.
+
+use crate::{normalize, Clause, Lit, Solver, Var};
+
+// Variables occurring in more clauses than this are not eliminated.
+const ELIM_OCCURRENCE_LIMIT: usize = 16;
+
+// A clause is only checked for being blocked on a literal whose negation
+// occurs in at most this many clauses.
+const BLOCKED_OCCURRENCE_LIMIT: usize = 16;
+
+impl Solver {
+    // Removes every clause that contains another clause (subsumption), and
+    // strengthens D by dropping -x when some clause C has x and all its other
+    // literals are in D (self-subsuming resolution: the resolvent of C and D on
+    // x is D without -x, and it subsumes D). Strengthened clauses are checked
+    // again as subsumers, since they may now subsume others.
+    pub(crate) fn subsume(&mut self) {
+        let mut occurs: Vec<Vec<usize>> = vec![Vec::new(); 2 * (self.num_vars + 1)];
+        for (idx, clause) in self.clauses.iter().enumerate() {
+            for &lit in clause {
+                occurs[lit.index()].push(idx);
+            }
+        }
+
+        let mut removed = vec![false; self.clauses.len()];
+        let mut marks = vec![false; 2 * (self.num_vars + 1)];
+        let mut queue: Vec<usize> = (0..self.clauses.len()).collect();
+        // popped from the back, so shortest clauses go first
+        queue.sort_by_key(|&idx| std::cmp::Reverse(self.clauses[idx].len()));
+
+        while let Some(c) = queue.pop() {
+            if removed[c] {
+                continue;
+            }
+            // only clauses sharing C's rarest variable can contain C
+            let Some(&pivot) = self.clauses[c]
+                .iter()
+                .min_by_key(|&&lit| occurs[lit.index()].len() + occurs[lit.negate().index()].len())
+            else {
+                // the empty clause subsumes everything
+                for (idx, gone) in removed.iter_mut().enumerate() {
+                    if idx != c && !*gone {
+                        *gone = true;
+                        self.log_preprocessing(true, &self.clauses[idx].clone());
+                    }
+                }
+                break;
+            };
+
+            for &lit in &self.clauses[c] {
+                marks[lit.index()] = true;
+            }
+            let mut candidates = occurs[pivot.index()].clone();
+            candidates.extend_from_slice(&occurs[pivot.negate().index()]);
+            for d in candidates {
+                if d == c || removed[d] || self.clauses[d].len() < self.clauses[c].len() {
+                    continue;
+                }
+                match subsumes(&self.clauses[c], &self.clauses[d], &marks) {
+                    Some(None) => {
+                        removed[d] = true;
+                        self.log_preprocessing(true, &self.clauses[d].clone());
+                    }
+                    Some(Some(flipped)) => {
+                        let old = self.clauses[d].clone();
+                        self.clauses[d].retain(|&lit| lit != flipped);
+                        self.log_preprocessing(false, &self.clauses[d].clone());
+                        self.log_preprocessing(true, &old);
+                        queue.push(d);
+                    }
+                    None => {}
+                }
+            }
+            for &lit in &self.clauses[c] {
+                marks[lit.index()] = false;
+            }
+        }
+
+        let mut idx = 0;
+        self.clauses.retain(|_| {
+            idx += 1;
+            !removed[idx - 1]
+        });
+    }
+
+    // Bounded variable elimination: a variable x occurring in few clauses is
+    // removed by replacing the clauses with x and those with -x by all their
+    // non-tautological resolvents on x, provided that doesn't make the formula
+    // bigger. The result is satisfiable exactly when the input is, and the
+    // removed clauses go on `elim_stack` so save_model() can give x a value.
+    pub(crate) fn eliminate(&mut self) {
+        let mut occurs: Vec<Vec<usize>> = vec![Vec::new(); 2 * (self.num_vars + 1)];
+        for (idx, clause) in self.clauses.iter().enumerate() {
+            for &lit in clause {
+                occurs[lit.index()].push(idx);
+            }
+        }
+        let mut removed = vec![false; self.clauses.len()];
+
+        // cheapest variables first; counts are taken once up front
+        let mut candidates: Vec<Var> = (1..=self.num_vars as Var)
+            .filter(|&var| {
+                let occurrences = occurs[Lit::new(var, true).index()].len()
+                    + occurs[Lit::new(var, false).index()].len();
+                occurrences > 0 && occurrences <= ELIM_OCCURRENCE_LIMIT
+            })
+            .collect();
+        candidates.sort_by_key(|&var| {
+            occurs[Lit::new(var, true).index()].len() * occurs[Lit::new(var, false).index()].len()
+        });
+
+        for var in candidates {
+            let pos_lit = Lit::new(var, true);
+            let neg_lit = Lit::new(var, false);
+            // occurrence lists are only ever appended to, so skip what is gone
+            let pos: Vec<usize> = occurs[pos_lit.index()]
+                .iter()
+                .copied()
+                .filter(|&c| !removed[c])
+                .collect();
+            let neg: Vec<usize> = occurs[neg_lit.index()]
+                .iter()
+                .copied()
+                .filter(|&c| !removed[c])
+                .collect();
+            if pos.len() + neg.len() > ELIM_OCCURRENCE_LIMIT {
+                continue;
+            }
+
+            let mut resolvents = Vec::new();
+            let mut too_many = false;
+            'outer: for &p in &pos {
+                for &n in &neg {
+                    if let Some(resolvent) = resolve(&self.clauses[p], &self.clauses[n], var) {
+                        if resolvents.len() == pos.len() + neg.len() {
+                            too_many = true;
+                            break 'outer;
+                        }
+                        resolvents.push(resolvent);
+                    }
+                }
+            }
+            if too_many {
+                continue;
+            }
+
+            for resolvent in resolvents {
+                self.log_preprocessing(false, &resolvent);
+                let idx = self.clauses.len();
+                for &lit in &resolvent {
+                    occurs[lit.index()].push(idx);
+                }
+                self.clauses.push(resolvent);
+                removed.push(false);
+            }
+            for (c, pivot) in pos
+                .iter()
+                .map(|&c| (c, pos_lit))
+                .chain(neg.iter().map(|&c| (c, neg_lit)))
+            {
+                removed[c] = true;
+                let clause = self.clauses[c].clone();
+                self.log_preprocessing(true, &clause);
+                self.elim_stack.push((pivot, clause));
+            }
+            self.eliminated[var as usize] = true;
+        }
+
+        let mut idx = 0;
+        self.clauses.retain(|_| {
+            idx += 1;
+            !removed[idx - 1]
+        });
+    }
+
+    // Blocked clause elimination: clause C is blocked on its literal x if
+    // every resolvent of C with a clause containing -x is a tautology, and
+    // removing it keeps the formula satisfiable but may add models. C goes on
+    // `elim_stack` with x, so save_model() makes x true if the model leaves C
+    // false, which can't falsify a remaining clause. Removing a clause can
+    // block the clauses resolving with it, so those are checked again.
+    pub(crate) fn eliminate_blocked(&mut self) {
+        let mut occurs: Vec<Vec<usize>> = vec![Vec::new(); 2 * (self.num_vars + 1)];
+        for (idx, clause) in self.clauses.iter().enumerate() {
+            for &lit in clause {
+                occurs[lit.index()].push(idx);
+            }
+        }
+        let mut removed = vec![false; self.clauses.len()];
+        let mut marks = vec![false; 2 * (self.num_vars + 1)];
+        let mut queued = vec![true; self.clauses.len()];
+        let mut queue: Vec<usize> = (0..self.clauses.len()).collect();
+
+        while let Some(c) = queue.pop() {
+            queued[c] = false;
+            if removed[c] {
+                continue;
+            }
+            for &lit in &self.clauses[c] {
+                marks[lit.index()] = true;
+            }
+            let blocking = self.clauses[c].iter().copied().find(|&lit| {
+                let others = &occurs[lit.negate().index()];
+                others.len() <= BLOCKED_OCCURRENCE_LIMIT
+                    && others.iter().all(|&d| {
+                        removed[d]
+                            || self.clauses[d].iter().any(|&other| {
+                                other != lit.negate() && marks[other.negate().index()]
+                            })
+                    })
+            });
+            for &lit in &self.clauses[c] {
+                marks[lit.index()] = false;
+            }
+            let Some(blocking) = blocking else {
+                continue;
+            };
+
+            removed[c] = true;
+            let clause = self.clauses[c].clone();
+            self.log_preprocessing(true, &clause);
+            for &lit in &clause {
+                for &d in &occurs[lit.negate().index()] {
+                    if !removed[d] && !queued[d] {
+                        queued[d] = true;
+                        queue.push(d);
+                    }
+                }
+            }
+            self.elim_stack.push((blocking, clause));
+        }
+
+        let mut idx = 0;
+        self.clauses.retain(|_| {
+            idx += 1;
+            !removed[idx - 1]
+        });
+    }
+
+    // Equivalent literal substitution: the binary clauses form an implication
+    // graph with the edges -a -> b and -b -> a for each clause (a b), and the
+    // literals of a strongly connected component imply each other, so all
+    // are equivalent. Every literal is replaced by the representative of its
+    // component, the one with the lowest variable, and the other variables
+    // are eliminated, with the two clauses tying each to its representative
+    // on `elim_stack` for save_model(). A component holding both x and -x
+    // makes the formula unsatisfiable, which adds the units x and -x instead.
+    pub(crate) fn substitute_equivalences(&mut self) {
+        let num_lits = 2 * (self.num_vars + 1);
+        let mut edges: Vec<Vec<usize>> = vec![Vec::new(); num_lits];
+        for clause in self.clauses.iter().filter(|clause| clause.len() == 2) {
+            let (a, b) = (clause[0], clause[1]);
+            edges[a.negate().index()].push(b.index());
+            edges[b.negate().index()].push(a.index());
+        }
+        let component = strongly_connected_components(&edges);
+
+        // a component and its mirror image have the same lowest variable, so
+        // the representative of -x is always the negation of that of x
+        let mut lowest: Vec<Option<Lit>> = vec![None; num_lits];
+        for var in 1..=self.num_vars as Var {
+            for lit in [Lit::new(var, true), Lit::new(var, false)] {
+                let slot = &mut lowest[component[lit.index()]];
+                if slot.is_none() {
+                    *slot = Some(lit);
+                }
+            }
+        }
+        for var in 1..=self.num_vars as Var {
+            let lit = Lit::new(var, true);
+            if component[lit.index()] == component[lit.negate().index()] {
+                for unit in [lit, lit.negate()] {
+                    self.log_preprocessing(false, &[unit]);
+                    self.clauses.push(vec![unit]);
+                }
+                return;
+            }
+        }
+        let representative = |lit: Lit| lowest[component[lit.index()]].unwrap_or(lit);
+
+        // every rewritten clause is added before any is deleted, as the proof
+        // needs the old binary clauses to justify the new ones
+        let mut deleted = Vec::new();
+        let mut rewritten = Vec::with_capacity(self.clauses.len());
+        for mut clause in std::mem::take(&mut self.clauses) {
+            if clause.iter().all(|&lit| representative(lit) == lit) {
+                rewritten.push(clause);
+                continue;
+            }
+            deleted.push(clause.clone());
+            for lit in &mut clause {
+                *lit = representative(*lit);
+            }
+            if normalize(&mut clause) {
+                self.log_preprocessing(false, &clause);
+                rewritten.push(clause);
+            }
+        }
+        for clause in deleted {
+            self.log_preprocessing(true, &clause);
+        }
+        self.clauses = rewritten;
+
+        for var in 1..=self.num_vars as Var {
+            let lit = Lit::new(var, true);
+            let rep = representative(lit);
+            if rep != lit {
+                self.elim_stack.push((lit, vec![lit, rep.negate()]));
+                self.elim_stack.push((lit.negate(), vec![lit.negate(), rep]));
+                self.eliminated[var as usize] = true;
+            }
+        }
+    }
+
+    // Records a clause preprocessing added or deleted for the DRAT proof.
+    fn log_preprocessing(&mut self, deleted: bool, clause: &[Lit]) {
+        if self.proof.is_some() {
+            self.log_proof(deleted, clause);
+        } else {
+            self.pending_proof.push((deleted, clause.to_vec()));
+        }
+    }
+}
+
+// Tarjan's algorithm over the graph with an edge from each node to each of
+// `edges[node]`, run with an explicit stack since implication chains can be
+// far longer than the call stack allows. Returns the component of every node;
+// nodes share a component exactly when each reaches the other.
+fn strongly_connected_components(edges: &[Vec<usize>]) -> Vec<usize> {
+    const UNVISITED: usize = usize::MAX;
+    let mut index = vec![UNVISITED; edges.len()];
+    let mut low = vec![0; edges.len()];
+    let mut on_stack = vec![false; edges.len()];
+    let mut component = vec![0; edges.len()];
+    let mut stack = Vec::new();
+    let mut next_index = 0;
+    let mut num_components = 0;
+
+    for root in 0..edges.len() {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        // nodes being visited, each with the position of its next edge
+        let mut calls = vec![(root, 0)];
+        index[root] = next_index;
+        low[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some((node, edge)) = calls.last_mut() {
+            let node = *node;
+            if let Some(&next) = edges[node].get(*edge) {
+                *edge += 1;
+                if index[next] == UNVISITED {
+                    index[next] = next_index;
+                    low[next] = next_index;
+                    next_index += 1;
+                    stack.push(next);
+                    on_stack[next] = true;
+                    calls.push((next, 0));
+                } else if on_stack[next] {
+                    low[node] = low[node].min(index[next]);
+                }
+                continue;
+            }
+
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                low[parent] = low[parent].min(low[node]);
+            }
+            if low[node] == index[node] {
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    component[member] = num_components;
+                    if member == node {
+                        break;
+                    }
+                }
+                num_components += 1;
+            }
+        }
+    }
+    component
+}
+
+// Resolvent of `p` (containing `var` positively) and `n` (containing it
+// negatively) on `var`, or None if it is a tautology.
+fn resolve(p: &Clause, n: &Clause, var: Var) -> Option<Clause> {
+    let mut resolvent: Clause = p.iter().copied().filter(|lit| lit.var() != var).collect();
+    for &lit in n {
+        if lit.var() == var || resolvent.contains(&lit) {
+            continue;
+        }
+        if resolvent.contains(&lit.negate()) {
+            return None;
+        }
+        resolvent.push(lit);
+    }
+    Some(resolvent)
+}
+
+// With the literals of `c` marked, decides whether `c` subsumes `d`
+// (Some(None)) or all of `c` but one literal x is in `d` together with -x,
+// so `d` can drop -x (Some(Some(-x))). Both clauses are free of repeats,
+// which normalize() sees to.
+fn subsumes(c: &Clause, d: &Clause, marks: &[bool]) -> Option<Option<Lit>> {
+    let mut found = 0;
+    let mut flipped = None;
+    for &lit in d {
+        if marks[lit.index()] {
+            found += 1;
+        } else if marks[lit.negate().index()] {
+            if flipped.is_some() {
+                return None;
+            }
+            flipped = Some(lit);
+        }
+    }
+    if found + usize::from(flipped.is_some()) == c.len() {
+        Some(flipped)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{brute_force_sat, clauses, lits, pigeonhole, random_3sat};
+    use crate::{check_model, Solver, SolverConfig};
+
+    #[test]
+    fn subsumed_clauses_are_removed() {
+        let solver = Solver::new(clauses(&[&[1, 2, 3], &[2, 1], &[-3, 4], &[4, -3, 1]]), 4);
+        assert_eq!(solver.clauses, clauses(&[&[1, 2], &[-3, 4]]));
+    }
+
+    #[test]
+    fn self_subsumption_strengthens_clauses() {
+        // (1 v 2) resolves with (-1 v 2 v 3) to (2 v 3), which replaces it
+        let solver = Solver::new(clauses(&[&[1, 2], &[-1, 2, 3]]), 3);
+        assert_eq!(solver.clauses, clauses(&[&[1, 2], &[2, 3]]));
+        // a strengthened clause goes on to subsume others
+        let solver = Solver::new(clauses(&[&[1, 2], &[-1, 2], &[2, 3, 4]]), 4);
+        assert_eq!(solver.clauses, vec![lits(&[2])]);
+    }
+
+    #[test]
+    fn subsumption_keeps_verdicts() {
+        for seed in 0..20 {
+            let formula = random_3sat(12, 54, seed);
+            let expected = brute_force_sat(&formula, 12);
+            let mut solver = Solver::new(formula.clone(), 12);
+            assert_eq!(solver.solve(), expected, "seed {seed}");
+            if expected {
+                assert!(check_model(&formula, &solver.model().unwrap()), "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn elimination_keeps_the_verdict() {
+        let mut formulas = vec![
+            (clauses(&[&[1, 2], &[-1, 3], &[2, 3, 4]]), 4),
+            (clauses(&[&[1, 2], &[-1, 2], &[1, -2], &[-1, -2]]), 2),
+            (clauses(&[&[1], &[-1, 2], &[-2, 3], &[-3, 4], &[-4, -1]]), 4),
+            (clauses(&[&[1, 2, 3], &[-1, -2], &[-2, -3], &[-1, -3], &[2, 4], &[-4, 5]]), 5),
+            pigeonhole(3),
+            pigeonhole(4),
+        ];
+        formulas.extend((0..10).map(|seed| (random_3sat(12, 50, seed), 12)));
+        for (formula, num_vars) in formulas {
+            let expected = Solver::new(formula.clone(), num_vars).solve();
+            let config = SolverConfig {
+                eliminate: true,
+                ..SolverConfig::default()
+            };
+            let mut solver = Solver::with_config(formula.clone(), num_vars, config);
+            assert_eq!(solver.solve(), expected, "{:?}", formula);
+            if expected {
+                assert!(check_model(&formula, &solver.model().unwrap()), "{:?}", formula);
+            }
+        }
+    }
+
+    fn merging() -> SolverConfig {
+        SolverConfig {
+            equivalences: true,
+            ..SolverConfig::default()
+        }
+    }
+
+    #[test]
+    fn equivalent_literals_are_merged() {
+        // 1 -> 2 -> -3 -> 1 is a cycle, so 2 and -3 are replaced by 1
+        let formula = clauses(&[&[-1, 2], &[-2, -3], &[3, 1], &[2, 4, 5], &[-3, -4], &[-5, 3]]);
+        let mut solver = Solver::with_config(formula.clone(), 5, merging());
+        assert!(solver.eliminated[2] && solver.eliminated[3]);
+        assert!(!solver.eliminated[1]);
+        assert!(solver.clauses.iter().flatten().all(|lit| lit.var() != 2 && lit.var() != 3));
+
+        // the model gives the merged variables their values back
+        assert!(solver.solve());
+        let model = solver.model().unwrap();
+        assert!(check_model(&formula, &model));
+        assert_eq!((model[2], model[3]), (model[1], !model[1]));
+        assert_eq!(model[5], !model[1]);
+
+        for seed in 0..10 {
+            let mut formula = random_3sat(10, 30, seed);
+            formula.extend(clauses(&[&[-1, 2], &[-2, 3], &[-3, 1], &[4, 5], &[-4, -5]]));
+            let expected = brute_force_sat(&formula, 10);
+            let mut solver = Solver::with_config(formula.clone(), 10, merging());
+            assert_eq!(solver.solve(), expected, "seed {seed}");
+            if expected {
+                assert!(check_model(&formula, &solver.model().unwrap()), "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn literal_equivalent_to_its_negation_is_unsat() {
+        // 1 -> 2 -> -1 and -1 -> 3 -> 1
+        let formula = clauses(&[&[-1, 2], &[-2, -1], &[1, 3], &[-3, 1], &[4, 5]]);
+        let mut solver = Solver::with_config(formula, 5, merging());
+        assert!(!solver.solve());
+    }
+
+    #[test]
+    fn blocked_clause_is_removed() {
+        // (-1 -2) is blocked on -1: its only resolvent, with (1 2), has 2 and -2
+        let formula = clauses(&[&[1, 2], &[-1, -2], &[-2, 3]]);
+        let config = SolverConfig {
+            blocked_clauses: true,
+            ..SolverConfig::default()
+        };
+        let mut solver = Solver::with_config(formula.clone(), 3, config);
+        let blocked = formula[1].clone();
+        assert!(!solver.clauses.contains(&blocked));
+        assert!(solver.elim_stack.iter().any(|(_, clause)| *clause == blocked));
+
+        // every clause goes, and the model of the empty formula sets all
+        // variables true, which falsifies (-1 -2) until it is repaired
+        assert!(solver.clauses.is_empty());
+        assert!(solver.solve());
+        assert!(check_model(&formula, &solver.model().unwrap()));
+    }
+}
@@ -0,0 +1,84 @@
+//! Formula builders shared by the unit tests.
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use crate::{Clause, Lit, Var};
+
+pub(crate) fn lits(lits: &[i32]) -> Clause {
+    lits.iter().map(|&lit| Lit::from(lit)).collect()
+}
+
+pub(crate) fn clauses(formula: &[&[i32]]) -> Vec<Clause> {
+    formula.iter().map(|clause| lits(clause)).collect()
+}
+
+/// Places `holes + 1` pigeons into `holes` holes, one pigeon per hole:
+/// unsatisfiable, and hard for resolution. Pigeon `p` in hole `h` is
+/// variable `p * holes + h + 1`.
+pub(crate) fn pigeonhole(holes: usize) -> (Vec<Clause>, usize) {
+    let var = |pigeon: usize, hole: usize| Lit::from((pigeon * holes + hole + 1) as i32);
+    let mut formula: Vec<Clause> = (0..=holes)
+        .map(|pigeon| (0..holes).map(|hole| var(pigeon, hole)).collect())
+        .collect();
+    for hole in 0..holes {
+        for a in 0..=holes {
+            for b in a + 1..=holes {
+                formula.push(vec![-var(a, hole), -var(b, hole)]);
+            }
+        }
+    }
+    (formula, (holes + 1) * holes)
+}
+
+/// Whether some assignment to variables 1..=`num_vars` satisfies `formula`,
+/// found by trying them all.
+pub(crate) fn brute_force_sat(formula: &[Clause], num_vars: usize) -> bool {
+    (0..1u64 << num_vars).any(|bits| {
+        let value = |lit: Lit| (bits >> (lit.var() - 1) & 1 == 1) == lit.is_positive();
+        formula.iter().all(|clause| clause.iter().any(|&lit| value(lit)))
+    })
+}
+
+/// A random 3-CNF formula over `num_vars` variables with `num_clauses`
+/// clauses, the same for the same `seed`.
+pub(crate) fn random_3sat(num_vars: usize, num_clauses: usize, seed: u64) -> Vec<Clause> {
+    let mut state = seed;
+    let mut next = |bound: usize| {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((state >> 33) % bound as u64) as usize
+    };
+    (0..num_clauses)
+        .map(|_| {
+            (0..3)
+                .map(|_| {
+                    let var = (next(num_vars) + 1) as Var;
+                    Lit::new(var, next(2) == 0)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A `Write` sink whose contents can still be read after it is handed to the
+/// solver as a `Box<dyn Write>`.
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuf(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuf {
+    pub(crate) fn contents(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+impl Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+// Parity constraints, kept out of the clause database and propagated by
+// Gaussian elimination over GF(2).
+
+use crate::{Clause, Lit, Solver, Var};
+
+// One bit per column (variable) set in the constraint, plus its parity.
+#[derive(Clone)]
+struct Row {
+    bits: Vec<u64>,
+    rhs: bool,
+}
+
+impl Row {
+    fn has(&self, col: usize) -> bool {
+        self.bits[col / 64] & (1 << (col % 64)) != 0
+    }
+
+    fn add(&mut self, other: &Row) {
+        for (word, &bits) in self.bits.iter_mut().zip(&other.bits) {
+            *word ^= bits;
+        }
+        self.rhs ^= other.rhs;
+    }
+
+    fn columns(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bits.iter().enumerate().flat_map(|(w, &word)| {
+            (0..64).filter(move |b| word & (1 << b) != 0).map(move |b| 64 * w + b)
+        })
+    }
+}
+
+// The XOR constraints of a solver as a matrix whose columns are the variables
+// they mention.
+#[derive(Clone, Default)]
+pub(crate) struct XorSystem {
+    rows: Vec<Row>,
+    // variable of each column, and the column of each variable if it has one
+    columns: Vec<usize>,
+    column_of: Vec<Option<usize>>,
+}
+
+impl XorSystem {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub(crate) fn variables(&self) -> &[usize] {
+        &self.columns
+    }
+
+    pub(crate) fn contains(&self, var: usize) -> bool {
+        self.column_of.get(var).is_some_and(|col| col.is_some())
+    }
+
+    // Adds the constraint that an odd number of `lits` is true. A repeated
+    // variable cancels out and a negative literal flips the parity.
+    fn add(&mut self, lits: &[Lit]) {
+        let mut vars = Vec::with_capacity(lits.len());
+        let mut rhs = true;
+        for &lit in lits {
+            let var = lit.var() as usize;
+            if self.column_of.len() <= var {
+                self.column_of.resize(var + 1, None);
+            }
+            if self.column_of[var].is_none() {
+                self.column_of[var] = Some(self.columns.len());
+                self.columns.push(var);
+            }
+            vars.push(var);
+            rhs ^= !lit.is_positive();
+        }
+
+        let words = self.columns.len().div_ceil(64);
+        for row in &mut self.rows {
+            row.bits.resize(words, 0);
+        }
+        let mut row = Row {
+            bits: vec![0; words],
+            rhs,
+        };
+        for var in vars {
+            let col = self.column_of[var].unwrap();
+            row.bits[col / 64] ^= 1 << (col % 64);
+        }
+        self.rows.push(row);
+    }
+
+    // Whether `model` (indexed by variable) satisfies every constraint.
+    pub(crate) fn satisfied(&self, model: &[bool]) -> bool {
+        self.rows.iter().all(|row| {
+            let parity = row
+                .columns()
+                .fold(false, |parity, col| parity ^ model[self.columns[col]]);
+            parity == row.rhs
+        })
+    }
+}
+
+impl Solver {
+    /// Adds the constraint that the XOR of `lits` is true, e.g. `[1, -2, 3]`
+    /// for x1 ^ !x2 ^ x3 (equivalently x1 ^ x2 ^ x3 = false). XOR constraints
+    /// are propagated by Gaussian elimination whenever unit propagation
+    /// reaches a fixpoint, and the clauses that explain its implications
+    /// and conflicts join the learned clauses. They are taken into account
+    /// from the next solve on. A DRAT proof can't justify these clauses,
+    /// so it only holds for formulas without XOR constraints. Variables
+    /// above `num_vars` are added to the formula, as with `add_clause`.
+    pub fn add_xor(&mut self, lits: &[Lit]) {
+        self.use_vars(lits);
+        self.xors.add(lits);
+    }
+
+    // Reduces the XOR system under the current assignment to row echelon form
+    // over its unassigned columns. A row left without unassigned variables
+    // but with the wrong parity is a conflict, which is returned as the index
+    // of a clause falsified by the assignment; a row left with a single
+    // unassigned variable implies its value, which is enqueued with a clause
+    // as its reason.
+    pub(crate) fn propagate_xors(&mut self) -> Option<usize> {
+        let mut rows = self.xors.rows.clone();
+        let mut pivots = 0;
+        for col in 0..self.xors.columns.len() {
+            if self.assignment.get(self.xors.columns[col]).is_some() {
+                continue;
+            }
+            let Some(r) = (pivots..rows.len()).find(|&r| rows[r].has(col)) else {
+                continue;
+            };
+            rows.swap(pivots, r);
+            let pivot = rows[pivots].clone();
+            for (i, row) in rows.iter_mut().enumerate() {
+                if i != pivots && row.has(col) {
+                    row.add(&pivot);
+                }
+            }
+            pivots += 1;
+        }
+
+        let mut implied = Vec::new();
+        for row in &rows {
+            let mut unassigned = None;
+            let mut open = 0;
+            let mut parity = false;
+            let mut reason: Clause = Vec::new();
+            for col in row.columns() {
+                let var = self.xors.columns[col];
+                match self.assignment.get(var) {
+                    Some(value) => {
+                        parity ^= value;
+                        reason.push(Lit::new(var as Var, !value));
+                    }
+                    None => {
+                        open += 1;
+                        unassigned = Some(var);
+                    }
+                }
+            }
+            match (open, unassigned) {
+                (0, _) if parity != row.rhs => return Some(self.add_xor_clause(reason)),
+                (1, Some(var)) => {
+                    reason.insert(0, Lit::new(var as Var, parity != row.rhs));
+                    implied.push(reason);
+                }
+                _ => {}
+            }
+        }
+
+        for reason in implied {
+            let lit = reason[0];
+            let idx = self.add_xor_clause(reason);
+            self.enqueue(lit, Some(idx));
+        }
+        None
+    }
+
+    // Adds a clause derived from the XOR constraints, ordering its false
+    // literals so the most recently assigned ones come first and get watched.
+    fn add_xor_clause(&mut self, mut clause: Clause) -> usize {
+        let start = usize::from(clause.first().is_some_and(|&lit| self.val(lit).is_none()));
+        clause[start..].sort_by_key(|lit| std::cmp::Reverse(self.levels[lit.var() as usize]));
+
+        // even an empty clause counts as learned
+        let lbd = self.compute_lbd(&clause).max(1);
+        self.push_clause(clause, lbd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{clauses, lits};
+    use crate::{SolveResult, Solver, SolverConfig};
+
+    #[test]
+    fn parity_system_is_unsat_on_its_own() {
+        // x1 ^ x2, x2 ^ x3 and x1 ^ x3 add up to 0 = 1, while the clause
+        // alone is satisfiable
+        let mut solver = Solver::new(vec![lits(&[1, 2, 3])], 3);
+        assert!(solver.solve());
+        for xor in [[1, 2], [2, 3], [1, 3]] {
+            solver.add_xor(&lits(&xor));
+        }
+        assert_eq!(solver.solve_with_limit(u64::MAX), SolveResult::Unsat);
+    }
+
+    #[test]
+    fn xor_over_a_new_variable() {
+        let mut solver = Solver::new(vec![lits(&[1, 2])], 2);
+        solver.add_xor(&lits(&[3, 1]));
+        assert!(solver.solve());
+        let model = solver.model().unwrap();
+        assert!(model[1] || model[2]);
+        assert!(model[3] != model[1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "removed by variable elimination")]
+    fn xor_over_an_eliminated_variable() {
+        let config = SolverConfig {
+            eliminate: true,
+            ..SolverConfig::default()
+        };
+        let mut solver = Solver::with_config(clauses(&[&[1, 2], &[-1, 3], &[2, 3, 4]]), 4, config);
+        let var = (1..=4).find(|&var| solver.eliminated[var]).unwrap();
+        solver.add_xor(&lits(&[var as i32, 5]));
+    }
+}
@@ -0,0 +1,18 @@
+use sat_solver::{Clause, Lit, Solver};
+
+fn clauses(formula: &[&[i32]]) -> Vec<Clause> {
+    formula.iter().map(|clause| clause.iter().map(|&lit| Lit::from(lit)).collect()).collect()
+}
+
+#[test]
+fn solves_through_the_public_api() {
+    let formula = clauses(&[&[1, 2], &[-1, 3], &[-3, 4], &[-2, -4, 5], &[-5, -1]]);
+    let mut solver = Solver::new(formula.clone(), 5);
+    assert!(solver.solve());
+    for clause in &formula {
+        assert!(clause.iter().any(|&lit| solver.val(lit) == Some(true)), "{:?}", clause);
+    }
+
+    let mut solver = Solver::new(clauses(&[&[1], &[-1, 2], &[-2]]), 2);
+    assert!(!solver.solve());
+}